@@ -0,0 +1,127 @@
+use crate::config::SeedingConfig;
+use crate::dna::GenomeKind;
+
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub struct Options {
+    pub headless: bool,
+    /// Experiment config file, overridden by other options
+    pub config: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub speed: usize,
+    pub snapshot: PathBuf,
+    pub autosave: Option<usize>,
+    pub load: bool,
+    pub programs: Vec<PathBuf>,
+    /// Genome kinds of spawned creatures, default when empty
+    pub genomes: Vec<GenomeKind>,
+    /// Genome library or snapshot to seed creatures from
+    pub library: Option<PathBuf>,
+    pub seeding: Option<SeedingConfig>,
+    /// Save the hall of fame there
+    pub hall_of_fame: Option<PathBuf>,
+    /// Export the lineage tree there
+    pub phylogeny: Option<PathBuf>,
+    /// Stream per-turn statistics there
+    pub stats: Option<PathBuf>,
+    /// Exit after the given turns
+    pub turns: Option<usize>,
+    /// Run the sweep spec there instead, ignoring other options
+    pub sweep: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn from_args() -> Self {
+        let mut options = Self {
+            headless: cfg!(feature = "headless") || !cfg!(feature = "windowed"),
+            config: None,
+            seed: None,
+            speed: 1,
+            snapshot: PathBuf::from("snapshot.ron"),
+            autosave: None,
+            load: false,
+            programs: Vec::new(),
+            genomes: Vec::new(),
+            library: None,
+            seeding: None,
+            hall_of_fame: None,
+            phylogeny: None,
+            stats: None,
+            turns: None,
+            sweep: None,
+        };
+
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--windowed" if cfg!(feature = "windowed") => options.headless = false,
+                "--windowed" => {
+                    eprintln!("Built without the `windowed` feature");
+                    std::process::exit(2);
+                }
+                "--config" => options.config = Some(parse_value(&arg, args.next())),
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())),
+                "--speed" => options.speed = parse_value(&arg, args.next()),
+                "--snapshot" => options.snapshot = parse_value(&arg, args.next()),
+                "--autosave" => options.autosave = Some(parse_value(&arg, args.next())),
+                "--program" => options.programs.push(parse_value(&arg, args.next())),
+                "--genome" => options.genomes.push(parse_value(&arg, args.next())),
+                "--library" => options.library = Some(parse_value(&arg, args.next())),
+                "--seeding" => options.seeding = Some(parse_seeding(&arg, args.next())),
+                "--hall-of-fame" => options.hall_of_fame = Some(parse_value(&arg, args.next())),
+                "--phylogeny" => options.phylogeny = Some(parse_value(&arg, args.next())),
+                "--stats" => options.stats = Some(parse_value(&arg, args.next())),
+                "--turns" => options.turns = Some(parse_value(&arg, args.next())),
+                "--sweep" => options.sweep = Some(parse_value(&arg, args.next())),
+                "--load" => {
+                    options.snapshot = parse_value(&arg, args.next());
+                    options.load = true;
+                }
+                _ => {
+                    eprintln!("Unknown argument: {}", arg);
+                    std::process::exit(2);
+                }
+            }
+        }
+
+        options
+    }
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: Option<String>) -> T {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(value)) => value,
+        _ => {
+            eprintln!("Invalid value for {}", arg);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// `<random>,<library>,<elite>[,<elite count>]` ratios of `SeedingConfig`
+fn parse_seeding(arg: &str, value: Option<String>) -> SeedingConfig {
+    let value = value.unwrap_or_default();
+    let values: Vec<_> = value
+        .split(',')
+        .map(|value| Some(value.to_owned()))
+        .collect();
+
+    if values.len() != 3 && values.len() != 4 {
+        eprintln!("Invalid value for {}", arg);
+        std::process::exit(2);
+    }
+
+    SeedingConfig {
+        random: parse_value(arg, values[0].clone()),
+        library: parse_value(arg, values[1].clone()),
+        elite: parse_value(arg, values[2].clone()),
+        elite_count: match values.get(3) {
+            Some(count) => parse_value(arg, count.clone()),
+            None => SeedingConfig::default().elite_count,
+        },
+    }
+}
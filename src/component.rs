@@ -1,77 +1,340 @@
-use crate::dna::DNA;
-use crate::utils::calculate_move_cost;
+use crate::config::{
+    ActionConfig, InterpreterConfig, MatingConfig, MetabolismConfig, MutationConfig,
+};
+use crate::constants::BaseType;
+use crate::dna::{Actions, RunError, DNA};
+use crate::hall_of_fame::CreatureRecord;
+use crate::snapshot::{CreatureSnapshot, FoodSnapshot};
+#[cfg(test)]
+use crate::utils::test_rng;
+use crate::utils::ResumableRng;
 
-use bevy::math::{const_vec2, Vec2};
+use bevy::math::{Vec2, Vec3};
 use bevy::prelude::Timer;
-
-use std::time::Duration;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 
 pub struct SimulationUi;
 
 pub struct Wall;
 
+/// Marks a spawned creature until the phylogeny registers it
+pub struct Newborn;
+
+/// Why a creature was despawned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DeathCause {
+    /// Not enough life to live one more turn
+    Starvation,
+    /// Haven't moved at all
+    Inactivity,
+    OutOfBox,
+    StepLimit,
+    NoOutput,
+    MalformedJump,
+}
+
+impl DeathCause {
+    pub const ALL: [DeathCause; 6] = [
+        DeathCause::Starvation,
+        DeathCause::Inactivity,
+        DeathCause::OutOfBox,
+        DeathCause::StepLimit,
+        DeathCause::NoOutput,
+        DeathCause::MalformedJump,
+    ];
+}
+
+impl From<RunError> for DeathCause {
+    fn from(err: RunError) -> Self {
+        match err {
+            RunError::StepLimit { .. } => DeathCause::StepLimit,
+            RunError::NoOutput { .. } => DeathCause::NoOutput,
+            RunError::MalformedJump { .. } => DeathCause::MalformedJump,
+        }
+    }
+}
+
 pub struct Creature {
+    /// Given by the phylogeny at the first step
+    id: Option<u64>,
+    parents: Vec<u64>,
+    birth_turn: usize,
     life: f32,
     old: usize,
     generation: usize,
     activated: bool,
     velocity: Vec2,
     tick_timer: Timer,
+    error: Option<RunError>,
+    /// Interpreter steps used in this turn
+    steps: usize,
+    /// Actions of the last program run
+    actions: Actions,
+    sexual: bool,
+    food_eaten: usize,
+    offspring: usize,
+    /// Mutations applied to the DNA inherited from parents
+    mutations: usize,
+    rng: ResumableRng,
     dna: DNA,
 }
 
 impl Creature {
-    pub const INIT_X: usize = 40;
-    pub const INIT_Y: usize = 40;
-    pub const INIT_SIZE: Vec2 = const_vec2!([Self::INIT_X as f32, Self::INIT_Y as f32]);
-
-    pub fn new() -> Self {
+    pub fn new(dna: DNA, rng: ResumableRng, config: &ActionConfig) -> Self {
         Self {
+            id: None,
+            parents: Vec::new(),
+            birth_turn: 0,
             life: 0.0,
             old: 0,
             generation: 0,
             activated: false,
             velocity: Vec2::new(0.0, 0.0),
-            tick_timer: Timer::new(Duration::from_millis(100), true),
-            dna: DNA::generate_prechecked(),
+            tick_timer: Timer::from_seconds(config.init_tick_interval, true),
+            error: None,
+            steps: 0,
+            actions: Actions::default(),
+            sexual: false,
+            food_eaten: 0,
+            offspring: 0,
+            mutations: 0,
+            rng,
+            dna,
         }
     }
 
-    fn child(parent: &Self) -> Self {
+    pub fn from_snapshot(snapshot: CreatureSnapshot) -> Self {
+        let mut tick_timer = Timer::from_seconds(snapshot.tick_interval, true);
+        tick_timer.elapsed = snapshot.tick_elapsed;
+
+        Self {
+            id: snapshot.id,
+            parents: snapshot.parents,
+            birth_turn: snapshot.birth_turn,
+            life: snapshot.life,
+            old: snapshot.old,
+            generation: snapshot.generation,
+            activated: snapshot.activated,
+            velocity: snapshot.velocity.into(),
+            tick_timer,
+            error: None,
+            steps: snapshot.steps,
+            actions: snapshot.actions,
+            sexual: snapshot.sexual,
+            food_eaten: snapshot.food_eaten,
+            offspring: snapshot.offspring,
+            mutations: snapshot.mutations,
+            rng: ResumableRng::from_state(snapshot.rng),
+            dna: snapshot.dna,
+        }
+    }
+
+    pub fn snapshot(&self, translation: Vec3) -> CreatureSnapshot {
+        CreatureSnapshot {
+            translation: translation.into(),
+            id: self.id,
+            parents: self.parents.clone(),
+            birth_turn: self.birth_turn,
+            life: self.life,
+            old: self.old,
+            generation: self.generation,
+            activated: self.activated,
+            velocity: self.velocity.into(),
+            tick_interval: self.tick_timer.duration,
+            tick_elapsed: self.tick_timer.elapsed,
+            steps: self.steps,
+            actions: self.actions,
+            sexual: self.sexual,
+            food_eaten: self.food_eaten,
+            offspring: self.offspring,
+            mutations: self.mutations,
+            rng: self.rng.state(),
+            dna: self.dna.clone(),
+        }
+    }
+
+    fn child(parent: &mut Self, config: &MutationConfig) -> Self {
+        let rng = ResumableRng::from_rng(&mut parent.rng).unwrap();
+        let mut dna = parent.dna.clone();
+        let mutations = dna.mutate(&mut parent.rng, config);
+        parent.offspring += 1;
+
+        let parents = parent.id.into_iter().collect();
+        Self::offspring(parent, rng, dna, parents, mutations, parent.generation + 1)
+    }
+
+    fn offspring(
+        parent: &Self,
+        rng: ResumableRng,
+        dna: DNA,
+        parents: Vec<u64>,
+        mutations: usize,
+        generation: usize,
+    ) -> Self {
         Self {
+            id: None,
+            parents,
+            birth_turn: 0,
             life: 0.0,
             old: 0,
-            generation: parent.generation + 1,
+            generation,
             activated: false,
             velocity: -parent.velocity,
             tick_timer: parent.tick_timer.clone(),
-            dna: parent.dna.duplicate(),
+            error: None,
+            steps: 0,
+            actions: Actions::default(),
+            sexual: parent.sexual,
+            food_eaten: 0,
+            offspring: 0,
+            mutations,
+            rng,
+            dna,
         }
     }
 
-    pub fn try_eat_food(&mut self, food: &mut Food) -> bool {
+    /// Reproduce only by mating, with `MatingConfig`
+    pub fn sexual(mut self, sexual: bool) -> Self {
+        self.sexual = sexual;
+        self
+    }
+
+    pub fn is_sexual(&self) -> bool {
+        self.sexual
+    }
+
+    pub fn can_mate(&self, config: &MatingConfig, metabolism: &MetabolismConfig) -> bool {
+        self.sexual && self.activated && self.life > config.cost + self.time_cost(metabolism)
+    }
+
+    /// Pay the mating cost and make an offspring with the genome of the partner
+    pub fn mate(
+        &mut self,
+        partner_dna: &DNA,
+        partner_id: Option<u64>,
+        partner_generation: usize,
+        config: &MatingConfig,
+        mutation: &MutationConfig,
+    ) -> Self {
+        self.life -= config.cost;
+        self.offspring += 1;
+
+        let rng = ResumableRng::from_rng(&mut self.rng).unwrap();
+        let mut dna = self
+            .dna
+            .crossover(partner_dna, config.crossover, &mut self.rng);
+        let mutations = dna.mutate(&mut self.rng, mutation);
+
+        let parents = self.id.into_iter().chain(partner_id).collect();
+        let generation = self.generation.max(partner_generation) + 1;
+        Self::offspring(self, rng, dna, parents, mutations, generation)
+    }
+
+    pub fn try_eat_food(&mut self, food: &mut Food, config: &MetabolismConfig) -> bool {
         if food.try_ate() {
-            self.life += 2.0;
+            self.life += config.food_energy;
+            self.food_eaten += 1;
             true
         } else {
             false
         }
     }
 
-    pub fn time_pass(&mut self) {
+    /// Life cost of the current turn
+    pub fn time_cost(&self, config: &MetabolismConfig) -> f32 {
+        let cost = self
+            .dna
+            .time_cost(self.steps, self.velocity.length(), config);
+
+        if self.actions.rest {
+            cost * config.resting
+        } else {
+            cost
+        }
+    }
+
+    pub fn time_pass(&mut self, config: &MetabolismConfig) {
         self.old += 1;
-        self.life -= self.dna.time_cost();
+        self.life -= self.time_cost(config);
+        self.steps = 0;
     }
 
-    pub fn will_die(&self) -> bool {
-        !self.activated || self.life < self.dna.time_cost()
+    pub fn will_die(&self, config: &MetabolismConfig) -> bool {
+        self.death_cause(config).is_some()
     }
 
-    pub fn has_moved(&mut self, distance: f32) {
+    pub fn death_cause(&self, config: &MetabolismConfig) -> Option<DeathCause> {
+        if let Some(err) = self.error {
+            Some(err.into())
+        } else if !self.activated {
+            Some(DeathCause::Inactivity)
+        } else if self.life < self.time_cost(config) {
+            Some(DeathCause::Starvation)
+        } else {
+            None
+        }
+    }
+
+    /// Error of the last failed program run
+    pub fn error(&self) -> Option<RunError> {
+        self.error
+    }
+
+    pub fn has_moved(&mut self, distance: f32, config: &MetabolismConfig) {
         if distance >= 0.0 {
             self.activated = true;
         }
-        self.life -= calculate_move_cost(distance);
+        self.life -= config.move_cost(distance);
+    }
+
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    pub fn parents(&self) -> &[u64] {
+        &self.parents
+    }
+
+    pub fn birth_turn(&self) -> usize {
+        self.birth_turn
+    }
+
+    pub fn mutations(&self) -> usize {
+        self.mutations
+    }
+
+    /// Set the ID given by the phylogeny
+    pub fn register(&mut self, id: u64, birth_turn: usize) {
+        self.id = Some(id);
+        self.birth_turn = birth_turn;
+    }
+
+    pub fn life(&self) -> f32 {
+        self.life
+    }
+
+    /// Take up to `amount` of life, returns how much was taken
+    pub fn drain(&mut self, amount: f32) -> f32 {
+        let drained = amount.min(self.life.max(0.0));
+        self.life -= drained;
+        drained
+    }
+
+    pub fn gain(&mut self, amount: f32) {
+        self.life += amount;
+    }
+
+    pub fn can_eat(&self, config: &ActionConfig) -> bool {
+        !config.explicit_eat || self.actions.eat
+    }
+
+    pub fn is_attacking(&self) -> bool {
+        self.actions.attack
+    }
+
+    pub fn signal(&self) -> BaseType {
+        self.actions.signal
     }
 
     pub fn velocity(&self) -> Vec2 {
@@ -86,20 +349,79 @@ impl Creature {
         self.old
     }
 
-    pub fn tick(&mut self, translation: Vec2, delta: f32) -> Result<(), ()> {
-        self.tick_timer.tick(delta);
+    pub fn dna(&self) -> &DNA {
+        &self.dna
+    }
+
+    /// Count an offspring made with the genome of this creature as partner
+    pub fn add_offspring(&mut self) {
+        self.offspring += 1;
+    }
 
-        if self.tick_timer.finished {
-            self.velocity = self.dna.move_behaivor(translation)?;
+    pub fn record(&self) -> CreatureRecord {
+        CreatureRecord {
+            id: self.id,
+            generation: self.generation,
+            old: self.old,
+            food_eaten: self.food_eaten,
+            offspring: self.offspring,
+            dna: self.dna.clone(),
         }
+    }
+
+    /// Whether the program should run in this step
+    pub fn tick(&mut self, delta: f32) -> bool {
+        self.tick_timer.tick(delta);
+        self.tick_timer.finished
+    }
+
+    /// Run the program with sensor `input` and follow its decision
+    pub fn think(
+        &mut self,
+        input: &[BaseType],
+        config: &InterpreterConfig,
+        action_config: &ActionConfig,
+    ) -> Result<(), RunError> {
+        match self.dna.move_behaivor(input, config) {
+            Ok(decision) => {
+                self.actions = decision.actions;
+                self.velocity = if self.actions.rest {
+                    Vec2::zero()
+                } else {
+                    decision.velocity
+                };
+                self.steps += decision.steps;
+
+                if let Some(interval) = self.actions.tick_interval {
+                    self.tick_timer.duration = (interval as f32 * action_config.tick_interval_unit)
+                        .clamp(
+                            action_config.min_tick_interval,
+                            action_config.max_tick_interval,
+                        );
+                }
 
-        Ok(())
+                Ok(())
+            }
+            Err(err) => {
+                self.error = Some(err);
+                Err(err)
+            }
+        }
     }
 
-    pub fn try_duplicate(&mut self) -> Option<Self> {
-        if self.life > 1.0 + self.dna.time_cost() {
-            self.life -= 1.0;
-            Some(Self::child(self))
+    pub fn try_duplicate(
+        &mut self,
+        config: &MetabolismConfig,
+        action_config: &ActionConfig,
+        mutation: &MutationConfig,
+    ) -> Option<Self> {
+        if self.sexual || (action_config.explicit_reproduce && !self.actions.reproduce) {
+            return None;
+        }
+
+        if self.life > config.reproduction_cost + self.time_cost(config) {
+            self.life -= config.reproduction_cost;
+            Some(Self::child(self, mutation))
         } else {
             None
         }
@@ -111,14 +433,20 @@ pub struct Food {
 }
 
 impl Food {
-    pub const INIT_X: usize = 20;
-    pub const INIT_Y: usize = 20;
-    pub const INIT_SIZE: Vec2 = const_vec2!([Self::INIT_X as f32, Self::INIT_Y as f32]);
-
     pub fn new() -> Self {
         Self { is_ate: false }
     }
 
+    pub fn is_ate(&self) -> bool {
+        self.is_ate
+    }
+
+    pub fn snapshot(&self, translation: Vec3) -> FoodSnapshot {
+        FoodSnapshot {
+            translation: translation.into(),
+        }
+    }
+
     pub fn try_ate(&mut self) -> bool {
         if !self.is_ate {
             self.is_ate = true;
@@ -128,3 +456,40 @@ impl Food {
         }
     }
 }
+
+#[test]
+fn metabolism_test() {
+    let config = MetabolismConfig::default();
+    let action_config = ActionConfig::default();
+    let mutation = MutationConfig::default();
+    let mut creature = Creature::new("++++".parse().unwrap(), test_rng(), &action_config);
+    let mut food = Food::new();
+
+    assert!(creature.try_eat_food(&mut food, &config));
+    assert!(!creature.try_eat_food(&mut food, &config));
+    assert_eq!(creature.life(), config.food_energy);
+
+    creature.has_moved(10.0, &config);
+    let life = config.food_energy - config.move_cost(10.0);
+    assert_eq!(creature.life(), life);
+
+    let cost = creature.time_cost(&config);
+    assert_eq!(cost, config.cost(4, 0, 0.0));
+    assert_eq!(creature.death_cause(&config), None);
+
+    creature.time_pass(&config);
+    assert_eq!(creature.life(), life - cost);
+    assert_eq!(creature.old(), 1);
+
+    let child = creature
+        .try_duplicate(&config, &action_config, &mutation)
+        .unwrap();
+    assert_eq!(creature.life(), life - cost - config.reproduction_cost);
+    assert_eq!(child.life(), 0.0);
+    assert_eq!(child.death_cause(&config), Some(DeathCause::Inactivity));
+
+    // not enough life left to pay another duplication and the turn
+    assert!(creature
+        .try_duplicate(&config, &action_config, &mutation)
+        .is_none());
+}
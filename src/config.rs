@@ -0,0 +1,406 @@
+use crate::dna::GenomeKind;
+use crate::sensor::Sensor;
+
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+
+/// Spawning pace of the simulation
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub daily_creature_count: usize,
+    pub daily_food_count: usize,
+    /// Seconds of each turn
+    pub turn_interval: f32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            daily_creature_count: 1000,
+            daily_food_count: 150,
+            turn_interval: 0.5,
+        }
+    }
+}
+
+/// Size of the world and of the objects in it
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldConfig {
+    /// Width and height of the box centered on the origin
+    pub size: (usize, usize),
+    /// Zoom out of the camera
+    pub scale: f32,
+    pub creature: (usize, usize),
+    pub food: (usize, usize),
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            size: (15000, 10000),
+            scale: 10.0,
+            creature: (40, 40),
+            food: (20, 20),
+        }
+    }
+}
+
+impl WorldConfig {
+    pub fn bound(&self) -> Vec2 {
+        Vec2::new(self.size.0 as f32, self.size.1 as f32)
+    }
+
+    pub fn creature_size(&self) -> Vec2 {
+        Vec2::new(self.creature.0 as f32, self.creature.1 as f32)
+    }
+
+    pub fn food_size(&self) -> Vec2 {
+        Vec2::new(self.food.0 as f32, self.food.1 as f32)
+    }
+
+    pub fn is_out_of_box(&self, translation: Vec2) -> bool {
+        let half = self.bound() / 2.0;
+
+        translation.x().abs() >= half.x() || translation.y().abs() >= half.y()
+    }
+
+    /// Distance to the nearest wall, 0 outside of the box
+    pub fn wall_distance(&self, translation: Vec2) -> f32 {
+        let half = self.bound() / 2.0;
+
+        (half.x() - translation.x().abs())
+            .min(half.y() - translation.y().abs())
+            .max(0.0)
+    }
+}
+
+/// Limits of each program run
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InterpreterConfig {
+    pub step_budget: usize,
+    pub tape_size: usize,
+    /// Scale limits with the genes of each DNA
+    pub genetic_limits: bool,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            step_budget: 100000,
+            tape_size: 8196,
+            genetic_limits: false,
+        }
+    }
+}
+
+/// Coefficients of the life cost paid by each creature every turn
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetabolismConfig {
+    /// Paid regardless of anything else
+    pub base: f32,
+    /// Per instruction of the genome
+    pub per_instruction: f32,
+    /// Per interpreter step used in the turn
+    pub per_step: f32,
+    /// Per unit of the current speed
+    pub per_speed: f32,
+    /// Scale of the whole cost while resting
+    pub resting: f32,
+    /// Life gained by eating a food
+    pub food_energy: f32,
+    /// Life paid by a creature to duplicate, offspring start without life
+    pub reproduction_cost: f32,
+    /// Cost of moving `distance` is `distance ^ move_exponent / move_divisor`
+    pub move_exponent: f32,
+    pub move_divisor: f32,
+}
+
+impl Default for MetabolismConfig {
+    fn default() -> Self {
+        Self {
+            base: 0.3,
+            per_instruction: 0.000_1,
+            per_step: 0.000_001,
+            per_speed: 0.000_1,
+            resting: 0.5,
+            food_energy: 2.0,
+            reproduction_cost: 1.0,
+            move_exponent: 1.2,
+            move_divisor: 80.0,
+        }
+    }
+}
+
+impl MetabolismConfig {
+    pub fn cost(&self, genome_len: usize, steps: usize, speed: f32) -> f32 {
+        self.base
+            + genome_len as f32 * self.per_instruction
+            + steps as f32 * self.per_step
+            + speed * self.per_speed
+    }
+
+    pub fn move_cost(&self, distance: f32) -> f32 {
+        distance.powf(self.move_exponent) / self.move_divisor
+    }
+}
+
+/// Sensors fed to creature programs, in input order
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensorConfig {
+    pub sensors: Vec<Sensor>,
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            sensors: Sensor::ALL.to_vec(),
+        }
+    }
+}
+
+impl SensorConfig {
+    /// Cells of program input written by the sensors
+    pub fn width(&self) -> usize {
+        self.sensors.iter().map(|sensor| sensor.width()).sum()
+    }
+}
+
+/// How creatures act on the output cells after velocity
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActionConfig {
+    /// Reproduce only when the program asks to, otherwise whenever life is enough
+    pub explicit_reproduce: bool,
+    /// Eat only when the program asks to, otherwise every touched food
+    pub explicit_eat: bool,
+    /// Life drained per second from a touched creature while attacking
+    pub attack_damage: f32,
+    /// Seconds of each unit of the requested tick interval
+    pub tick_interval_unit: f32,
+    pub min_tick_interval: f32,
+    pub max_tick_interval: f32,
+    /// Tick interval of spawned creatures, in seconds
+    pub init_tick_interval: f32,
+}
+
+impl Default for ActionConfig {
+    fn default() -> Self {
+        Self {
+            explicit_reproduce: false,
+            explicit_eat: false,
+            attack_damage: 1.0,
+            tick_interval_unit: 0.01,
+            min_tick_interval: 0.05,
+            max_tick_interval: 2.0,
+            init_tick_interval: 0.1,
+        }
+    }
+}
+
+/// How two parent genomes are combined
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Crossover {
+    /// Head of one parent and tail of the other
+    OnePoint,
+    /// Middle of one parent in the other
+    TwoPoint,
+    /// Each instruction from a random parent
+    Uniform,
+}
+
+/// Sexual reproduction between touching creatures
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatingConfig {
+    pub enabled: bool,
+    /// Ratio of sexual creatures among spawned ones, offspring inherit it
+    pub sexual_ratio: f32,
+    pub crossover: Crossover,
+    /// Life paid by each parent
+    pub cost: f32,
+}
+
+impl Default for MatingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sexual_ratio: 0.5,
+            crossover: Crossover::OnePoint,
+            cost: 1.0,
+        }
+    }
+}
+
+/// Expected count of each mutation operator per reproduction
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationConfig {
+    /// Replace an instruction
+    pub point: f32,
+    /// Insert a random instruction
+    pub insertion: f32,
+    /// Remove an instruction
+    pub deletion: f32,
+    /// Repeat a segment right after itself
+    pub duplication: f32,
+    /// Reverse a segment
+    pub inversion: f32,
+    /// Move a segment elsewhere
+    pub transposition: f32,
+    /// Longest segment of the segment operators
+    pub max_segment: usize,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Scale of every operator rate
+    pub rate: f32,
+    /// Also scale rates with the mutation rate gene of each DNA
+    pub heritable_rate: bool,
+    /// Standard deviation of the log-normal self-adaptation of the rate gene
+    pub rate_adaptation: f32,
+}
+
+impl Default for MutationConfig {
+    fn default() -> Self {
+        Self {
+            point: 2.0,
+            insertion: 0.5,
+            deletion: 0.5,
+            duplication: 0.1,
+            inversion: 0.1,
+            transposition: 0.1,
+            max_segment: 16,
+            min_length: None,
+            max_length: None,
+            rate: 1.0,
+            heritable_rate: false,
+            rate_adaptation: 0.2,
+        }
+    }
+}
+
+/// Genome representations of spawned creatures
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenomeConfig {
+    /// Used in turn, repeat a kind to spawn it more often
+    pub kinds: Vec<GenomeKind>,
+}
+
+impl Default for GenomeConfig {
+    fn default() -> Self {
+        Self {
+            kinds: vec![GenomeKind::Bf],
+        }
+    }
+}
+
+/// Background generation of random DNA
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PoolConfig {
+    /// Generated DNA waiting to be taken
+    pub capacity: usize,
+    /// Worker threads, 0 for one per core
+    pub threads: usize,
+    /// Keep only DNA deciding within the interpreter limits
+    pub filter: bool,
+    /// Least output cells written by a kept DNA
+    pub min_outputs: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            threads: 0,
+            filter: true,
+            min_outputs: 2,
+        }
+    }
+}
+
+/// Where the DNA of creatures spawned each turn comes from
+///
+/// Ratios are relative weights, a source without DNA to pick is ignored
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeedingConfig {
+    /// Random DNA from the pool
+    pub random: f32,
+    /// DNA sampled from the genome library
+    pub library: f32,
+    /// Mutants of the fittest living creatures
+    pub elite: f32,
+    /// Number of fittest creatures mutants are taken from
+    pub elite_count: usize,
+}
+
+impl Default for SeedingConfig {
+    fn default() -> Self {
+        Self {
+            random: 1.0,
+            library: 0.0,
+            elite: 0.0,
+            elite_count: 10,
+        }
+    }
+}
+
+/// Archive of the most notable despawned creatures
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HallOfFameConfig {
+    /// Saved there when set
+    pub path: Option<PathBuf>,
+    /// Kept creatures in each category
+    pub size: usize,
+    /// Save at most every given turns
+    pub save_interval: usize,
+}
+
+impl Default for HallOfFameConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            size: 10,
+            save_interval: 100,
+        }
+    }
+}
+
+/// Export of the lineage tree
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhylogenyConfig {
+    /// Saved there when set, as Newick with a `nwk` or `newick` extension and CSV otherwise
+    pub path: Option<PathBuf>,
+    /// Save every given turns
+    pub save_interval: usize,
+    /// Keep creatures that died without living descendants, the tree then grows with every birth
+    pub keep_extinct: bool,
+}
+
+impl Default for PhylogenyConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            save_interval: 100,
+            keep_extinct: false,
+        }
+    }
+}
+
+/// Recording of per-turn statistics
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    /// Streamed there when set, as JSON Lines with a `jsonl` or `json` extension and CSV otherwise
+    pub path: Option<PathBuf>,
+}
@@ -1,74 +1,525 @@
 mod bf;
+mod genome;
+mod mutation;
+mod neural;
+mod pool;
+mod seeder;
 
-use self::bf::{run as run_bf, Instruction};
-use crate::utils::{convert_from_unit, convert_vec2_to_unit};
+pub use self::bf::RunError;
+pub use self::pool::DnaPool;
+pub use self::seeder::Seeder;
+
+use self::bf::{BfGenome, Instruction, Limits};
+use self::genome::Genome;
+use self::neural::NeuralGenome;
+use crate::config::{Crossover, InterpreterConfig, MetabolismConfig, MutationConfig};
+use crate::constants::BaseType;
+use crate::utils::convert_from_unit;
 use bevy::prelude::Vec2;
-use once_cell::sync::Lazy;
-use crossbeam_channel::Receiver;
-use rayon::prelude::*;
-use rand::distributions::Standard;
-use rand::{thread_rng, Rng};
-
-static DNA_STORAGE: Lazy<Receiver<DNA>> = Lazy::new(|| {
-    let (tx, rx) = crossbeam_channel::bounded(1024);
-
-    std::thread::spawn(move || {
-        std::iter::repeat_with(DNA::generate)
-            .par_bridge()
-            .for_each(move |dna| {
-                tx.send(dna).unwrap();
-            });
-    });
-
-    rx
-});
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
-pub struct DNA {
-    code: Vec<Instruction>,
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Heritable parameters besides the code
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Genes {
+    /// Scale of `InterpreterConfig::step_budget` when limits are genetic
+    pub step_budget: f32,
+    /// Scale of `InterpreterConfig::tape_size` when limits are genetic
+    pub tape_size: f32,
+    /// Scale of `MutationConfig` rates when the rate is heritable
+    pub mutation_rate: f32,
 }
 
-impl DNA {
-    pub fn generate() -> Self {
-        let rng = thread_rng();
+impl Default for Genes {
+    fn default() -> Self {
+        Self {
+            step_budget: 1.0,
+            tape_size: 1.0,
+            mutation_rate: 1.0,
+        }
+    }
+}
+
+impl Genes {
+    const MIN_SCALE: f32 = 0.1;
+    const MAX_SCALE: f32 = 10.0;
+
+    /// Each gene from a random parent
+    fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Self {
+        let mut pick = |a: f32, b: f32| if rng.gen() { a } else { b };
 
         Self {
-            code: rng.sample_iter(Standard).take(2048).collect(),
+            step_budget: pick(self.step_budget, other.step_budget),
+            tape_size: pick(self.tape_size, other.tape_size),
+            mutation_rate: pick(self.mutation_rate, other.mutation_rate),
         }
     }
 
-    pub fn generate_prechecked() -> Self {
-        DNA_STORAGE.recv().unwrap()
+    fn mutate(&mut self, rng: &mut impl Rng, config: &MutationConfig) {
+        let normal = Normal::new(0.0, 0.05).unwrap();
+
+        for scale in [&mut self.step_budget, &mut self.tape_size].iter_mut() {
+            **scale = (**scale * (normal.sample(rng) as f32).exp())
+                .clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+        }
+
+        if config.heritable_rate {
+            // a non-finite adaptation is rejected by validation, keep the rate if it slips through
+            if let Ok(normal) = Normal::new(0.0, config.rate_adaptation.abs()) {
+                self.mutation_rate = (self.mutation_rate * normal.sample(rng).exp())
+                    .clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+            }
+        }
     }
+}
 
-    pub fn move_behaivor(&self, translation: Vec2) -> Result<Vec2, ()> {
-        let (x, y) = convert_vec2_to_unit(translation);
-        let output = run_bf(&self.code, &[x, y])?;
+/// Actions decided by the output cells after velocity, in this order
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Actions {
+    pub reproduce: bool,
+    pub eat: bool,
+    pub attack: bool,
+    /// Stop moving and pay less life
+    pub rest: bool,
+    /// Value perceived by nearby creatures
+    pub signal: BaseType,
+    /// Requested tick interval, in units of `ActionConfig::tick_interval_unit`
+    pub tick_interval: Option<BaseType>,
+}
+
+impl Actions {
+    fn from_output(output: &[BaseType]) -> Self {
+        let cell = |idx: usize| output.get(idx).copied();
+        let flag = |idx: usize| cell(idx).unwrap_or(0) != 0;
+
+        Self {
+            reproduce: flag(0),
+            eat: flag(1),
+            attack: flag(2),
+            rest: flag(3),
+            signal: cell(4).unwrap_or(0),
+            tick_interval: cell(5),
+        }
+    }
+}
+
+/// Decided behavior of a program run
+pub struct Decision {
+    pub velocity: Vec2,
+    pub actions: Actions,
+    /// Interpreter steps used to decide
+    pub steps: usize,
+    /// Number of output cells written
+    pub outputs: usize,
+}
+
+impl Decision {
+    /// Velocity from the first two output cells, actions from the rest
+    fn from_output(output: &[BaseType], steps: usize) -> Self {
         let x = output.get(0).copied().map(convert_from_unit).unwrap_or(0.0);
         let y = output.get(1).copied().map(convert_from_unit).unwrap_or(0.0);
 
-        Ok(Vec2::new(x, y))
+        Self {
+            velocity: Vec2::new(x, y),
+            actions: Actions::from_output(output.get(2..).unwrap_or(&[])),
+            steps,
+            outputs: output.len(),
+        }
+    }
+}
+
+/// Genome representations, see `Genome`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GenomeKind {
+    /// BF program
+    Bf,
+    /// Feed-forward neural network
+    Neural,
+}
+
+impl GenomeKind {
+    pub const ALL: [GenomeKind; 2] = [GenomeKind::Bf, GenomeKind::Neural];
+}
+
+impl FromStr for GenomeKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "bf" => Ok(GenomeKind::Bf),
+            "neural" => Ok(GenomeKind::Neural),
+            _ => Err(format!("unknown genome kind `{}`", name)),
+        }
     }
+}
 
-    pub fn time_cost(&self) -> f32 {
-        // TODO: relate this value with code size
-        0.5
+#[derive(Clone)]
+enum Backend {
+    Bf(BfGenome),
+    Neural(NeuralGenome),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "DnaSource", try_from = "DnaSource")]
+pub struct DNA {
+    backend: Backend,
+    genes: Genes,
+}
+
+/// Serialized form of DNA, code is BF source
+#[derive(Serialize, Deserialize)]
+struct DnaSource {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    neural: Option<NeuralGenome>,
+    #[serde(default)]
+    genes: Genes,
+}
+
+/// Error of parsing DNA from BF source or neural weights
+#[derive(Debug, PartialEq)]
+pub enum ParseDnaError {
+    UnknownInstruction {
+        /// Character index in the source
+        position: usize,
+        symbol: char,
+    },
+    /// Index of a weight which isn't a finite number
+    InvalidWeight(usize),
+    WeightCount {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ParseDnaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseDnaError::UnknownInstruction { position, symbol } => {
+                write!(f, "unknown instruction `{}` at {}", symbol, position)
+            }
+            ParseDnaError::InvalidWeight(idx) => write!(f, "invalid neural weight {}", idx),
+            ParseDnaError::WeightCount { expected, found } => {
+                write!(f, "expected {} neural weights, found {}", expected, found)
+            }
+        }
     }
+}
 
-    pub fn mutate(&mut self) {
-        let mut rng = thread_rng();
+impl std::error::Error for ParseDnaError {}
 
-        for _ in 0..rng.gen_range(0, 5) {
-            let idx = rng.gen_range(0, self.code.len());
-            self.code[idx] = rng.gen();
+impl fmt::Display for DNA {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.backend {
+            Backend::Bf(genome) => genome.fmt(f),
+            Backend::Neural(genome) => genome.fmt(f),
         }
     }
+}
+
+/// Parse BF source, whitespaces are ignored
+///
+/// A source with digits holds whitespace separated neural weights, as a neural DNA is displayed
+impl FromStr for DNA {
+    type Err = ParseDnaError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        if source.chars().any(|symbol| symbol.is_ascii_digit()) {
+            let weights = source
+                .split_whitespace()
+                .enumerate()
+                .map(|(idx, weight)| match weight.parse::<f32>() {
+                    Ok(weight) if weight.is_finite() => Ok(weight),
+                    _ => Err(ParseDnaError::InvalidWeight(idx)),
+                })
+                .collect::<Result<_, _>>()?;
+
+            return NeuralGenome::from_weights(weights)
+                .map(|genome| Self::from_backend(Backend::Neural(genome)));
+        }
+
+        source
+            .chars()
+            .enumerate()
+            .filter(|(_, symbol)| !symbol.is_whitespace())
+            .map(|(position, symbol)| {
+                Instruction::from_symbol(symbol)
+                    .ok_or(ParseDnaError::UnknownInstruction { position, symbol })
+            })
+            .collect::<Result<_, _>>()
+            .map(|code| Self::from_backend(Backend::Bf(BfGenome::from_code(code))))
+    }
+}
+
+impl From<DNA> for DnaSource {
+    fn from(dna: DNA) -> Self {
+        match dna.backend {
+            Backend::Bf(genome) => Self {
+                code: genome.to_string(),
+                neural: None,
+                genes: dna.genes,
+            },
+            Backend::Neural(genome) => Self {
+                code: String::new(),
+                neural: Some(genome),
+                genes: dna.genes,
+            },
+        }
+    }
+}
+
+impl TryFrom<DnaSource> for DNA {
+    type Error = ParseDnaError;
+
+    fn try_from(source: DnaSource) -> Result<Self, Self::Error> {
+        let mut dna: DNA = match source.neural {
+            Some(genome) => Self::from_backend(Backend::Neural(genome)),
+            None => source.code.parse()?,
+        };
+        dna.genes = source.genes;
+        Ok(dna)
+    }
+}
 
-    pub fn duplicate(&self) -> Self {
+impl DNA {
+    fn from_backend(backend: Backend) -> Self {
+        Self {
+            backend,
+            genes: Genes::default(),
+        }
+    }
+
+    pub fn generate(kind: GenomeKind, rng: &mut impl Rng) -> Self {
+        Self::from_backend(match kind {
+            GenomeKind::Bf => Backend::Bf(BfGenome::generate(rng)),
+            GenomeKind::Neural => Backend::Neural(NeuralGenome::generate(rng)),
+        })
+    }
+
+    pub fn kind(&self) -> GenomeKind {
+        match self.backend {
+            Backend::Bf(_) => GenomeKind::Bf,
+            Backend::Neural(_) => GenomeKind::Neural,
+        }
+    }
+
+    fn limits(&self, config: &InterpreterConfig) -> Limits {
+        if config.genetic_limits {
+            Limits {
+                step_budget: (config.step_budget as f32 * self.genes.step_budget) as usize,
+                tape_size: (config.tape_size as f32 * self.genes.tape_size) as usize,
+            }
+        } else {
+            Limits {
+                step_budget: config.step_budget,
+                tape_size: config.tape_size,
+            }
+        }
+    }
+
+    pub fn move_behaivor(
+        &self,
+        input: &[BaseType],
+        config: &InterpreterConfig,
+    ) -> Result<Decision, RunError> {
+        let limits = self.limits(config);
+
+        match &self.backend {
+            Backend::Bf(genome) => genome.decide(input, limits),
+            Backend::Neural(genome) => genome.decide(input, limits),
+        }
+    }
+
+    /// Instructions or weights of the genome
+    pub fn size(&self) -> usize {
+        match &self.backend {
+            Backend::Bf(genome) => genome.size(),
+            Backend::Neural(genome) => genome.size(),
+        }
+    }
+
+    /// Life cost of a turn where the program used `steps` and moved at `speed`
+    pub fn time_cost(&self, steps: usize, speed: f32, config: &MetabolismConfig) -> f32 {
+        config.cost(self.size(), steps, speed)
+    }
+
+    /// Effective scale of the mutation operator rates
+    pub fn mutation_rate(&self, config: &MutationConfig) -> f32 {
+        if config.heritable_rate {
+            config.rate * self.genes.mutation_rate
+        } else {
+            config.rate
+        }
+    }
+
+    /// The rate gene is mutated first, so the genome is mutated with the new rate
+    ///
+    /// Returns the number of mutations applied to the genome
+    pub fn mutate(&mut self, rng: &mut impl Rng, config: &MutationConfig) -> usize {
+        self.genes.mutate(rng, config);
+        let rate = self.mutation_rate(config);
+
+        match &mut self.backend {
+            Backend::Bf(genome) => genome.mutate(rng, config, rate),
+            Backend::Neural(genome) => genome.mutate(rng, config, rate),
+        }
+    }
+
+    /// Combine with the genome of `other`, genomes of different kinds aren't combined
+    pub fn crossover(&self, other: &Self, method: Crossover, rng: &mut impl Rng) -> Self {
+        let backend = match (&self.backend, &other.backend) {
+            (Backend::Bf(a), Backend::Bf(b)) => Backend::Bf(a.crossover(b, method, rng)),
+            (Backend::Neural(a), Backend::Neural(b)) => {
+                Backend::Neural(a.crossover(b, method, rng))
+            }
+            (backend, _) => backend.clone(),
+        };
+
+        Self {
+            backend,
+            genes: self.genes.crossover(&other.genes, rng),
+        }
+    }
+
+    /// Whether it decides within the limits and writes at least `min_outputs` cells
+    ///
+    /// Checked on a zeroed input of `input_width` cells, the width sensors write
+    pub fn is_viable(
+        &self,
+        config: &InterpreterConfig,
+        min_outputs: usize,
+        input_width: usize,
+    ) -> bool {
+        match self.move_behaivor(&vec![0; input_width], config) {
+            Ok(decision) => decision.outputs >= min_outputs,
+            Err(_) => false,
+        }
+    }
+
+    pub fn duplicate(&self, rng: &mut impl Rng, config: &MutationConfig) -> Self {
         let mut new_dna = self.clone();
 
-        new_dna.mutate();
+        new_dna.mutate(rng, config);
 
         new_dna
     }
 }
+
+#[test]
+fn source_roundtrip_test() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let source = "+[->.<]@,";
+    let dna: DNA = source.parse().unwrap();
+
+    assert_eq!(dna.to_string(), source);
+    assert_eq!("+ +\n.".parse::<DNA>().unwrap().to_string(), "++.");
+
+    let neural = DNA::generate(GenomeKind::Neural, &mut StdRng::seed_from_u64(0));
+    let parsed: DNA = neural.to_string().parse().unwrap();
+    assert_eq!(parsed.kind(), GenomeKind::Neural);
+    assert_eq!(parsed.to_string(), neural.to_string());
+}
+
+#[test]
+fn source_error_test() {
+    assert_eq!(
+        "++x".parse::<DNA>().err(),
+        Some(ParseDnaError::UnknownInstruction {
+            position: 2,
+            symbol: 'x'
+        })
+    );
+    assert_eq!(
+        "0.5 -1".parse::<DNA>().err(),
+        Some(ParseDnaError::WeightCount {
+            expected: NeuralGenome::WEIGHTS,
+            found: 2
+        })
+    );
+    assert_eq!(
+        "0.5 x1".parse::<DNA>().err(),
+        Some(ParseDnaError::InvalidWeight(1))
+    );
+}
+
+#[test]
+fn crossover_test() {
+    let a: DNA = "++++".parse().unwrap();
+    let b: DNA = "------".parse().unwrap();
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..100 {
+        let child = a.crossover(&b, Crossover::OnePoint, &mut rng).to_string();
+        assert_eq!(child.len(), 6);
+        assert!(child.trim_start_matches('+').chars().all(|c| c == '-'));
+
+        let child = a.crossover(&b, Crossover::TwoPoint, &mut rng).to_string();
+        assert_eq!(child.len(), 4);
+
+        let child = a.crossover(&b, Crossover::Uniform, &mut rng).to_string();
+        assert!(child.len() == 4 || child.len() == 6);
+        assert!(child[4..].chars().all(|c| c == '-'));
+    }
+}
+
+#[test]
+fn genome_kind_test() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for &kind in GenomeKind::ALL.iter() {
+        let dna = DNA::generate(kind, &mut rng);
+        let source = ron::ser::to_string(&dna).unwrap();
+        let loaded: DNA = ron::de::from_str(&source).unwrap();
+
+        assert_eq!(loaded.kind(), kind);
+        assert_eq!(loaded.to_string(), dna.to_string());
+    }
+}
+
+#[test]
+fn actions_test() {
+    let actions = Actions::from_output(&[1, 0, 3, 0, 7]);
+
+    assert!(actions.reproduce);
+    assert!(!actions.eat);
+    assert!(actions.attack);
+    assert!(!actions.rest);
+    assert_eq!(actions.signal, 7);
+    assert_eq!(actions.tick_interval, None);
+}
+
+#[test]
+fn mutation_rate_test() {
+    let mut dna: DNA = "++++".parse().unwrap();
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut config = MutationConfig {
+        rate: 0.5,
+        ..MutationConfig::default()
+    };
+
+    dna.mutate(&mut rng, &config);
+    assert_eq!(dna.mutation_rate(&config), 0.5);
+
+    config.heritable_rate = true;
+    dna.mutate(&mut rng, &config);
+    assert!(dna.genes.mutation_rate != 1.0);
+    assert_eq!(dna.mutation_rate(&config), 0.5 * dna.genes.mutation_rate);
+}
@@ -1,22 +1,47 @@
+use super::genome::{crossover_slices, Genome};
+use super::{mutation, Decision};
+use crate::config::{Crossover, MutationConfig};
 use crate::constants::BaseType;
 
-use memchr::memchr;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
+use std::cell::RefCell;
+use std::fmt;
 use std::slice::Iter;
 
-pub fn run(code: &[Instruction], input: &[BaseType]) -> Result<Vec<BaseType>, ()> {
-    let mut interpreter = Interpreter::new(code, input);
+/// Compile and run BF program once
+#[cfg(test)]
+pub fn run(code: &[Instruction], input: &[BaseType]) -> Result<Vec<BaseType>, RunError> {
+    let mut interpreter = Interpreter::default();
 
-    if !interpreter.run() {
-        Err(())
-    } else {
-        Ok(interpreter.into_output())
+    interpreter
+        .run(&Program::compile(code), input, Limits::default())
+        .map(|execution| execution.output.to_vec())
+}
+
+#[derive(Copy, Clone)]
+pub struct Limits {
+    pub step_budget: usize,
+    pub tape_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            step_budget: 100000,
+            tape_size: 8196,
+        }
     }
 }
 
+/// Result of a successful run
+pub struct Execution<'a> {
+    pub output: &'a [BaseType],
+    pub steps: usize,
+}
+
 #[derive(Copy, Clone, FromPrimitive)]
 #[repr(u8)]
 pub enum Instruction {
@@ -40,128 +65,503 @@ pub enum Instruction {
     Halt,
 }
 
+impl Instruction {
+    pub fn symbol(self) -> char {
+        match self {
+            Instruction::DecPtr => '<',
+            Instruction::IncPtr => '>',
+            Instruction::DecVal => '-',
+            Instruction::IncVal => '+',
+            Instruction::Write => '.',
+            Instruction::Read => ',',
+            Instruction::JumpLeft => '[',
+            Instruction::JumpRight => ']',
+            Instruction::Halt => '@',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '<' => Some(Instruction::DecPtr),
+            '>' => Some(Instruction::IncPtr),
+            '-' => Some(Instruction::DecVal),
+            '+' => Some(Instruction::IncVal),
+            '.' => Some(Instruction::Write),
+            ',' => Some(Instruction::Read),
+            '[' => Some(Instruction::JumpLeft),
+            ']' => Some(Instruction::JumpRight),
+            '@' => Some(Instruction::Halt),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
 impl Distribution<Instruction> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Instruction {
         FromPrimitive::from_u8(rng.gen_range(0, 9)).unwrap_or_else(|| unreachable!())
     }
 }
 
-fn cast_instruction_as_bytes(instructions: &[Instruction]) -> &[u8] {
-    unsafe { instructions.align_to().1 }
+/// Index of the matching bracket for each `[` and `]`, `None` for unmatched ones
+fn match_brackets(code: &[Instruction]) -> Vec<Option<usize>> {
+    let mut jumps = vec![None; code.len()];
+    let mut lefts = Vec::new();
+
+    for (pc, inst) in code.iter().enumerate() {
+        match inst {
+            Instruction::JumpLeft => lefts.push(pc),
+            Instruction::JumpRight => {
+                if let Some(left) = lefts.pop() {
+                    jumps[left] = Some(pc);
+                    jumps[pc] = Some(left);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    jumps
 }
 
-struct Interpreter<'a> {
-    dead_count: usize,
-    pc: usize,
-    code: &'a [Instruction],
-    tape: Tape,
-    input: Iter<'a, BaseType>,
-    output: Vec<BaseType>,
+/// Why a program run failed, `pc` is the index of the instruction in the source code
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RunError {
+    /// Ran out of steps
+    StepLimit { pc: usize, steps: usize },
+    /// Finished without writing anything
+    NoOutput { steps: usize },
+    /// Jumped with an unmatched bracket
+    MalformedJump { pc: usize, steps: usize },
 }
 
-impl<'a> Interpreter<'a> {
-    pub fn new(code: &'a [Instruction], input: &'a [BaseType]) -> Self {
-        Self {
-            dead_count: 100000,
-            pc: 0,
-            code,
-            tape: Tape::new(8196),
-            input: input.into_iter(),
-            output: Vec::with_capacity(100),
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::StepLimit { pc, steps } => {
+                write!(f, "step limit exceeded at {} after {} steps", pc, steps)
+            }
+            RunError::NoOutput { steps } => write!(f, "no output after {} steps", steps),
+            RunError::MalformedJump { pc, steps } => {
+                write!(f, "unmatched bracket at {} after {} steps", pc, steps)
+            }
         }
     }
+}
+
+/// Compiled operation, `len` is the count of instructions folded into it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Op {
+    Move {
+        offset: isize,
+        len: usize,
+    },
+    Add {
+        val: BaseType,
+        len: usize,
+    },
+    /// `[-]` or `[+]`
+    Clear {
+        inc: bool,
+    },
+    Write,
+    Read,
+    /// `[`, jump to the given op when the value is zero, `None` if unmatched
+    JumpZero(Option<usize>),
+    /// `]`, jump to the given op when the value is not zero, `None` if unmatched
+    JumpNonZero(Option<usize>),
+    Halt,
+}
 
-    fn find_inst(&self, inst: Instruction) -> Option<usize> {
-        let bytes = cast_instruction_as_bytes(&self.code[self.pc..]);
-        memchr(inst as u8, bytes)
+impl Op {
+    /// Instructions the raw program would run for this op
+    fn steps(self, val: BaseType) -> usize {
+        match self {
+            Op::Move { len, .. } | Op::Add { len, .. } => len,
+            // `[` then `-]` or `+]` for each iteration
+            Op::Clear { inc: false } => val.saturating_mul(2).saturating_add(1),
+            Op::Clear { inc: true } => val.wrapping_neg().saturating_mul(2).saturating_add(1),
+            _ => 1,
+        }
     }
+}
 
-    pub fn run_inst(&mut self, inst: Instruction) {
-        match inst {
-            Instruction::DecPtr => self.tape.dec_ptr(),
-            Instruction::IncPtr => self.tape.inc_ptr(),
-            Instruction::DecVal => self.tape.dec_val(),
-            Instruction::IncVal => self.tape.inc_val(),
-            Instruction::Write => self.output.push(self.tape.val()),
-            Instruction::Read => self.tape.set_val(*self.input.next().unwrap_or(&0)),
-            Instruction::JumpLeft => {
-                if self.tape.val() == 0 {
-                    self.pc = self
-                        .find_inst(Instruction::JumpRight)
-                        .unwrap_or(self.code.len());
+/// BF program compiled once per DNA
+///
+/// Runs of `<>` and `+-` are folded, clear loops are recognized and jump targets are cached.
+/// Every op is charged with the steps the raw program would use so results are the same.
+#[derive(Clone)]
+pub struct Program {
+    ops: Vec<Op>,
+    /// Source index of each op
+    positions: Vec<usize>,
+}
+
+impl Program {
+    pub fn compile(code: &[Instruction]) -> Self {
+        let jumps = match_brackets(code);
+        let mut ops = Vec::with_capacity(code.len());
+        let mut positions = Vec::with_capacity(code.len());
+        // index of op for `[` of each unclosed loop
+        let mut lefts = Vec::new();
+        let mut pc = 0;
+
+        while let Some(&inst) = code.get(pc) {
+            // every iteration pushes exactly one op
+            positions.push(pc);
+
+            match inst {
+                Instruction::DecPtr | Instruction::IncPtr => {
+                    let folded = Self::fold(&code[pc..], |inst| {
+                        matches!(inst, Instruction::DecPtr | Instruction::IncPtr)
+                    });
+                    let offset = folded
+                        .iter()
+                        .map(|inst| match inst {
+                            Instruction::DecPtr => -1,
+                            _ => 1,
+                        })
+                        .sum();
+
+                    ops.push(Op::Move {
+                        offset,
+                        len: folded.len(),
+                    });
+                    pc += folded.len();
+                    continue;
                 }
-            }
-            Instruction::JumpRight => {
-                if self.tape.val() != 0 {
-                    self.pc = self.find_inst(Instruction::JumpLeft).unwrap_or(0);
+                Instruction::DecVal | Instruction::IncVal => {
+                    let folded = Self::fold(&code[pc..], |inst| {
+                        matches!(inst, Instruction::DecVal | Instruction::IncVal)
+                    });
+                    let val = folded.iter().fold(0 as BaseType, |val, inst| match inst {
+                        Instruction::DecVal => val.wrapping_sub(1),
+                        _ => val.wrapping_add(1),
+                    });
+
+                    ops.push(Op::Add {
+                        val,
+                        len: folded.len(),
+                    });
+                    pc += folded.len();
+                    continue;
                 }
+                Instruction::Write => ops.push(Op::Write),
+                Instruction::Read => ops.push(Op::Read),
+                Instruction::JumpLeft => match code.get(pc + 1..pc + 3) {
+                    Some([Instruction::DecVal, Instruction::JumpRight]) => {
+                        ops.push(Op::Clear { inc: false });
+                        pc += 3;
+                        continue;
+                    }
+                    Some([Instruction::IncVal, Instruction::JumpRight]) => {
+                        ops.push(Op::Clear { inc: true });
+                        pc += 3;
+                        continue;
+                    }
+                    _ => {
+                        if jumps[pc].is_some() {
+                            lefts.push(ops.len());
+                        }
+                        ops.push(Op::JumpZero(None));
+                    }
+                },
+                Instruction::JumpRight => match jumps[pc] {
+                    Some(_) => {
+                        let left = lefts.pop().unwrap_or_else(|| unreachable!());
+                        ops[left] = Op::JumpZero(Some(ops.len() + 1));
+                        ops.push(Op::JumpNonZero(Some(left + 1)));
+                    }
+                    None => ops.push(Op::JumpNonZero(None)),
+                },
+                Instruction::Halt => ops.push(Op::Halt),
             }
-            Instruction::Halt => {
-                self.pc = self.code.len();
-            }
+
+            pc += 1;
         }
+
+        Self { ops, positions }
+    }
+
+    /// Longest prefix of `code` that satisfies `f`
+    fn fold(code: &[Instruction], f: impl Fn(&Instruction) -> bool) -> &[Instruction] {
+        let len = code.iter().take_while(|inst| f(inst)).count();
+        &code[..len]
+    }
+
+    #[cfg(test)]
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
     }
-    pub fn run(&mut self) -> bool {
-        while let Some(&inst) = self.code.get(self.pc) {
-            match self.dead_count.checked_sub(1) {
-                Some(dead_count) => self.dead_count = dead_count,
-                None => return false,
+}
+
+/// Runs compiled programs, tape and output buffers are reused between runs
+#[derive(Default)]
+pub struct Interpreter {
+    tape: Tape,
+    output: Vec<BaseType>,
+}
+
+impl Interpreter {
+    pub fn run<'a>(
+        &'a mut self,
+        program: &Program,
+        input: &[BaseType],
+        limits: Limits,
+    ) -> Result<Execution<'a>, RunError> {
+        self.tape.reset(limits.tape_size);
+        self.output.clear();
+
+        let mut input = input.iter();
+        let mut used_steps: usize = 0;
+        let mut pc = 0;
+
+        while let Some(&op) = program.ops.get(pc) {
+            let steps = used_steps.saturating_add(op.steps(self.tape.val()));
+
+            if steps > limits.step_budget {
+                return Err(RunError::StepLimit {
+                    pc: program.positions[pc],
+                    steps: used_steps,
+                });
+            }
+
+            used_steps = steps;
+
+            let malformed_jump = RunError::MalformedJump {
+                pc: program.positions[pc],
+                steps,
+            };
+
+            pc += 1;
+
+            match op {
+                Op::Move { offset, .. } => self.tape.move_ptr(offset),
+                Op::Add { val, .. } => self.tape.set_val(self.tape.val().wrapping_add(val)),
+                Op::Clear { .. } => self.tape.set_val(0),
+                Op::Write => self.output.push(self.tape.val()),
+                Op::Read => self.tape.set_val(next_input(&mut input)),
+                Op::JumpZero(target) => {
+                    if self.tape.val() == 0 {
+                        pc = target.ok_or(malformed_jump)?;
+                    }
+                }
+                Op::JumpNonZero(target) => {
+                    if self.tape.val() != 0 {
+                        pc = target.ok_or(malformed_jump)?;
+                    }
+                }
+                Op::Halt => break,
             }
-            self.pc += 1;
-            self.run_inst(inst);
         }
 
-        true
+        Ok(Execution {
+            output: &self.output,
+            steps: used_steps,
+        })
     }
+}
 
-    pub fn into_output(self) -> Vec<BaseType> {
-        self.output
-    }
+fn next_input(input: &mut Iter<BaseType>) -> BaseType {
+    input.next().copied().unwrap_or(0)
 }
 
+#[derive(Default)]
 struct Tape {
     ptr: usize,
     bytes: Vec<BaseType>,
+    /// Cells that may be non zero
+    dirty: Vec<usize>,
 }
 
 impl Tape {
-    pub fn new(size: usize) -> Self {
-        Self {
-            ptr: 0,
-            bytes: vec![0; size],
+    /// Clear all cells and resize to `size`
+    pub fn reset(&mut self, size: usize) {
+        for idx in self.dirty.drain(..) {
+            self.bytes[idx] = 0;
         }
+
+        self.bytes.resize(size.max(1), 0);
+        self.ptr = 0;
     }
 
-    pub fn inc_ptr(&mut self) {
-        self.ptr += 1;
+    pub fn move_ptr(&mut self, offset: isize) {
+        let len = self.bytes.len() as isize;
+        self.ptr = (self.ptr as isize + offset % len).rem_euclid(len) as usize;
+    }
 
-        if self.ptr == self.bytes.len() {
-            self.ptr = 0;
+    pub fn set_val(&mut self, val: BaseType) {
+        if self.bytes[self.ptr] == 0 && val != 0 {
+            self.dirty.push(self.ptr);
         }
+
+        self.bytes[self.ptr] = val;
     }
 
-    pub fn dec_ptr(&mut self) {
-        self.ptr = self.ptr.checked_sub(1).unwrap_or(self.bytes.len() - 1);
+    pub fn val(&self) -> BaseType {
+        self.bytes[self.ptr]
     }
+}
+
+thread_local! {
+    static INTERPRETER: RefCell<Interpreter> = RefCell::new(Interpreter::default());
+}
+
+/// BF program, output cells are read by `Decision::from_output`
+#[derive(Clone)]
+pub struct BfGenome {
+    code: Vec<Instruction>,
+    /// Compiled from `code`, must be updated with it
+    program: Program,
+}
 
-    pub fn inc_val(&mut self) {
-        self.bytes[self.ptr] = self.bytes[self.ptr].wrapping_add(1);
+impl BfGenome {
+    pub fn from_code(code: Vec<Instruction>) -> Self {
+        Self {
+            program: Program::compile(&code),
+            code,
+        }
     }
+}
+
+impl fmt::Display for BfGenome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for inst in self.code.iter() {
+            write!(f, "{}", inst)?;
+        }
 
-    pub fn dec_val(&mut self) {
-        self.bytes[self.ptr] = self.bytes[self.ptr].wrapping_sub(1);
+        Ok(())
     }
+}
 
-    pub fn set_val(&mut self, val: BaseType) {
-        self.bytes[self.ptr] = val;
+impl Genome for BfGenome {
+    fn generate(rng: &mut impl Rng) -> Self {
+        Self::from_code(rng.sample_iter(Standard).take(2048).collect())
     }
 
-    pub fn val(&self) -> BaseType {
-        self.bytes[self.ptr]
+    fn mutate(&mut self, rng: &mut impl Rng, config: &MutationConfig, rate: f32) -> usize {
+        let count = mutation::mutate(&mut self.code, rng, config, rate);
+        self.program = Program::compile(&self.code);
+        count
+    }
+
+    fn crossover(&self, other: &Self, method: Crossover, rng: &mut impl Rng) -> Self {
+        Self::from_code(crossover_slices(&self.code, &other.code, method, rng))
+    }
+
+    fn decide(&self, input: &[BaseType], limits: Limits) -> Result<Decision, RunError> {
+        INTERPRETER.with(|interpreter| {
+            let mut interpreter = interpreter.borrow_mut();
+            let execution = interpreter.run(&self.program, input, limits)?;
+
+            if execution.output.is_empty() {
+                return Err(RunError::NoOutput {
+                    steps: execution.steps,
+                });
+            }
+
+            Ok(Decision::from_output(execution.output, execution.steps))
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.code.len()
     }
 }
 
 #[test]
 fn run_test() {
-    assert_eq!(run(&[Instruction::IncVal, Instruction::Write,], &[]), &[1]);
+    assert_eq!(
+        run(&[Instruction::IncVal, Instruction::Write,], &[]),
+        Ok(vec![1])
+    );
+}
+
+#[test]
+fn nested_loop_test() {
+    let code: Vec<_> = "++[>++[>+<-]<-]>>."
+        .chars()
+        .filter_map(Instruction::from_symbol)
+        .collect();
+
+    assert_eq!(run(&code, &[]), Ok(vec![4]));
+}
+
+#[test]
+fn unmatched_bracket_test() {
+    assert_eq!(
+        run(&[Instruction::IncVal, Instruction::JumpRight], &[]),
+        Err(RunError::MalformedJump { pc: 1, steps: 2 })
+    );
+    assert_eq!(
+        run(&[Instruction::JumpLeft], &[]),
+        Err(RunError::MalformedJump { pc: 0, steps: 1 })
+    );
+    // never executed
+    assert_eq!(
+        run(
+            &[Instruction::Write, Instruction::Halt, Instruction::JumpLeft],
+            &[]
+        ),
+        Ok(vec![0])
+    );
+}
+
+#[test]
+fn compile_test() {
+    let code: Vec<_> = ">><+-+[-]<[.]"
+        .chars()
+        .filter_map(Instruction::from_symbol)
+        .collect();
+
+    assert_eq!(
+        Program::compile(&code).ops(),
+        &[
+            Op::Move { offset: 1, len: 3 },
+            Op::Add { val: 1, len: 3 },
+            Op::Clear { inc: false },
+            Op::Move { offset: -1, len: 1 },
+            Op::JumpZero(Some(7)),
+            Op::Write,
+            Op::JumpNonZero(Some(5)),
+        ]
+    );
+}
+
+#[test]
+fn buffer_reuse_test() {
+    let mut interpreter = Interpreter::default();
+    let program = Program::compile(&[Instruction::Read, Instruction::Write]);
+    let limits = Limits {
+        step_budget: 10,
+        tape_size: 16,
+    };
+
+    let execution = interpreter.run(&program, &[3], limits).unwrap();
+    assert_eq!(execution.output, &[3]);
+    assert_eq!(execution.steps, 2);
+
+    let execution = interpreter.run(&program, &[], limits).unwrap();
+    assert_eq!(execution.output, &[0]);
+}
+
+#[test]
+fn step_limit_test() {
+    let code: Vec<_> = "+>+[]"
+        .chars()
+        .filter_map(Instruction::from_symbol)
+        .collect();
+
+    assert_eq!(
+        run(&code, &[]),
+        Err(RunError::StepLimit {
+            pc: 4,
+            steps: Limits::default().step_budget,
+        })
+    );
 }
@@ -0,0 +1,65 @@
+use super::bf::{Limits, RunError};
+use super::Decision;
+use crate::config::{Crossover, MutationConfig};
+use crate::constants::BaseType;
+
+use rand::Rng;
+
+/// Representation of a creature behavior which can evolve
+pub trait Genome: Sized {
+    fn generate(rng: &mut impl Rng) -> Self;
+
+    /// Mutate with every `MutationConfig` rate multiplied by `rate`, returns the applied count
+    fn mutate(&mut self, rng: &mut impl Rng, config: &MutationConfig, rate: f32) -> usize;
+
+    fn crossover(&self, other: &Self, method: Crossover, rng: &mut impl Rng) -> Self;
+
+    /// Decide behavior from sensor `input`
+    fn decide(&self, input: &[BaseType], limits: Limits) -> Result<Decision, RunError>;
+
+    /// Size charged by the metabolism per instruction
+    fn size(&self) -> usize;
+}
+
+/// Crossover of two sequences which can have different lengths
+pub fn crossover_slices<T: Copy>(
+    a: &[T],
+    b: &[T],
+    method: Crossover,
+    rng: &mut impl Rng,
+) -> Vec<T> {
+    let min_len = a.len().min(b.len());
+
+    match method {
+        Crossover::OnePoint => {
+            let point = rng.gen_range(0, min_len + 1);
+            a[..point].iter().chain(&b[point..]).copied().collect()
+        }
+        Crossover::TwoPoint => {
+            let first = rng.gen_range(0, min_len + 1);
+            let second = rng.gen_range(first, min_len + 1);
+            a[..first]
+                .iter()
+                .chain(&b[first..second])
+                .chain(&a[second..])
+                .copied()
+                .collect()
+        }
+        Crossover::Uniform => {
+            let len = if rng.gen() { a.len() } else { b.len() };
+            (0..len)
+                .map(|idx| match (a.get(idx), b.get(idx)) {
+                    (Some(&x), Some(&y)) => {
+                        if rng.gen() {
+                            x
+                        } else {
+                            y
+                        }
+                    }
+                    (Some(&x), None) | (None, Some(&x)) => x,
+                    (None, None) => unreachable!(),
+                })
+                .collect()
+        }
+    }
+}
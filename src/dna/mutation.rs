@@ -0,0 +1,166 @@
+use super::bf::Instruction;
+use crate::config::MutationConfig;
+
+use rand::Rng;
+
+#[derive(Clone, Copy)]
+enum Operator {
+    Point,
+    Insertion,
+    Deletion,
+    Duplication,
+    Inversion,
+    Transposition,
+}
+
+/// Apply each operator a Poisson distributed number of times, rates are multiplied by `scale`
+///
+/// Returns the number of applied operators
+pub fn mutate(
+    code: &mut Vec<Instruction>,
+    rng: &mut impl Rng,
+    config: &MutationConfig,
+    scale: f32,
+) -> usize {
+    let operators = [
+        (Operator::Point, config.point),
+        (Operator::Insertion, config.insertion),
+        (Operator::Deletion, config.deletion),
+        (Operator::Duplication, config.duplication),
+        (Operator::Inversion, config.inversion),
+        (Operator::Transposition, config.transposition),
+    ];
+
+    let mut count = 0;
+
+    for &(operator, rate) in operators.iter() {
+        let operator_count = sample_count(rng, rate * scale);
+        for _ in 0..operator_count {
+            apply(code, rng, operator, config);
+        }
+        count += operator_count;
+    }
+
+    count
+}
+
+/// Poisson sample by multiplying uniforms, rates are expected to be small
+pub fn sample_count(rng: &mut impl Rng, rate: f32) -> usize {
+    let limit = (-(rate.max(0.0) as f64)).exp();
+    let mut product: f64 = rng.gen();
+    let mut count = 0;
+
+    while product > limit {
+        product *= rng.gen::<f64>();
+        count += 1;
+    }
+
+    count
+}
+
+/// Random segment `(start, len)` of at least one instruction
+fn segment(len: usize, rng: &mut impl Rng, config: &MutationConfig) -> (usize, usize) {
+    let segment_len = rng.gen_range(1, config.max_segment.clamp(1, len) + 1);
+    let start = rng.gen_range(0, len - segment_len + 1);
+    (start, segment_len)
+}
+
+fn apply(
+    code: &mut Vec<Instruction>,
+    rng: &mut impl Rng,
+    operator: Operator,
+    config: &MutationConfig,
+) {
+    let len = code.len();
+    let min_length = config.min_length.unwrap_or(0);
+    let max_length = config.max_length.unwrap_or(usize::MAX);
+
+    match operator {
+        Operator::Insertion => {
+            if len < max_length {
+                code.insert(rng.gen_range(0, len + 1), rng.gen());
+            }
+        }
+        // every other operator needs an instruction to work on
+        _ if len == 0 => {}
+        Operator::Point => {
+            let idx = rng.gen_range(0, len);
+            code[idx] = rng.gen();
+        }
+        Operator::Deletion => {
+            if len > min_length {
+                code.remove(rng.gen_range(0, len));
+            }
+        }
+        Operator::Duplication => {
+            let (start, segment_len) = segment(len, rng, config);
+            if len.saturating_add(segment_len) <= max_length {
+                let copy = code[start..start + segment_len].to_vec();
+                let end = start + segment_len;
+                code.splice(end..end, copy);
+            }
+        }
+        Operator::Inversion => {
+            let (start, segment_len) = segment(len, rng, config);
+            code[start..start + segment_len].reverse();
+        }
+        Operator::Transposition => {
+            let (start, segment_len) = segment(len, rng, config);
+            let moved: Vec<_> = code.drain(start..start + segment_len).collect();
+            let to = rng.gen_range(0, code.len() + 1);
+            code.splice(to..to, moved);
+        }
+    }
+}
+
+#[test]
+fn length_limit_test() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let config = MutationConfig {
+        point: 1.0,
+        insertion: 5.0,
+        deletion: 5.0,
+        duplication: 2.0,
+        inversion: 1.0,
+        transposition: 1.0,
+        max_segment: 8,
+        min_length: Some(10),
+        max_length: Some(40),
+        ..MutationConfig::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut code: Vec<Instruction> = (0..20).map(|_| rng.gen()).collect();
+
+    for _ in 0..1000 {
+        mutate(&mut code, &mut rng, &config, 1.0);
+        assert!(code.len() >= 10 && code.len() <= 40);
+    }
+}
+
+#[test]
+fn segment_operator_test() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let config = MutationConfig::default();
+    let mut rng = StdRng::seed_from_u64(0);
+    let source: Vec<Instruction> = (0..50).map(|_| rng.gen()).collect();
+    let symbols = |code: &[Instruction]| {
+        let mut symbols: Vec<_> = code.iter().map(|inst| inst.symbol()).collect();
+        symbols.sort_unstable();
+        symbols
+    };
+
+    // inversion and transposition only reorder instructions
+    for &operator in [Operator::Inversion, Operator::Transposition].iter() {
+        let mut code = source.clone();
+        apply(&mut code, &mut rng, operator, &config);
+        assert_eq!(symbols(&code), symbols(&source));
+    }
+
+    let mut code = source.clone();
+    apply(&mut code, &mut rng, Operator::Duplication, &config);
+    assert!(code.len() > source.len());
+}
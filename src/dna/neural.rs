@@ -0,0 +1,201 @@
+use super::bf::{Limits, RunError};
+use super::genome::{crossover_slices, Genome};
+use super::mutation::sample_count;
+use super::{Actions, Decision, ParseDnaError};
+use crate::config::{Crossover, MutationConfig};
+use crate::constants::BaseType;
+use crate::utils::convert_from_unit;
+
+use bevy::prelude::Vec2;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+use std::convert::TryFrom;
+use std::fmt;
+
+const INPUTS: usize = 16;
+const HIDDEN: usize = 8;
+/// Velocity x and y, then each field of `Actions`
+const OUTPUTS: usize = 8;
+
+/// Scale of decoded sensor units fed to the network
+const INPUT_SCALE: f32 = 0.001;
+/// Speed of a saturated velocity output
+const MAX_SPEED: f32 = 200.0;
+/// Signal of a saturated signal output
+const MAX_SIGNAL: f32 = 255.0;
+/// Tick interval units of a saturated tick interval output
+const MAX_TICK_INTERVAL: f32 = 100.0;
+/// Standard deviation of a weight point mutation
+const WEIGHT_MUTATION: f32 = 0.5;
+
+/// Feed-forward network with one tanh hidden layer
+///
+/// Only point mutations of `MutationConfig` apply, the topology is fixed
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "NeuralSource")]
+pub struct NeuralGenome {
+    weights: Vec<f32>,
+}
+
+/// Serialized form of `NeuralGenome`, not checked against the topology yet
+#[derive(Deserialize)]
+struct NeuralSource {
+    weights: Vec<f32>,
+}
+
+impl TryFrom<NeuralSource> for NeuralGenome {
+    type Error = String;
+
+    fn try_from(source: NeuralSource) -> Result<Self, Self::Error> {
+        Self::from_weights(source.weights).map_err(|err| err.to_string())
+    }
+}
+
+impl NeuralGenome {
+    /// Biases are stored as the weight of an extra input fixed to 1
+    pub const WEIGHTS: usize = (INPUTS + 1) * HIDDEN + (HIDDEN + 1) * OUTPUTS;
+
+    pub fn from_weights(weights: Vec<f32>) -> Result<Self, ParseDnaError> {
+        if weights.len() == Self::WEIGHTS {
+            Ok(Self { weights })
+        } else {
+            Err(ParseDnaError::WeightCount {
+                expected: Self::WEIGHTS,
+                found: weights.len(),
+            })
+        }
+    }
+}
+
+/// Weights separated by spaces, exact enough to be parsed back by `DNA::from_str`
+impl fmt::Display for NeuralGenome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, weight) in self.weights.iter().enumerate() {
+            if idx != 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", weight)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `tanh(bias + weights . input)` of each output, weights of each output are contiguous
+fn layer(weights: &[f32], input: &[f32], output: &mut [f32]) {
+    for (neuron, out) in weights.chunks(input.len() + 1).zip(output.iter_mut()) {
+        let sum = neuron[0]
+            + neuron[1..]
+                .iter()
+                .zip(input)
+                .map(|(weight, x)| weight * x)
+                .sum::<f32>();
+        *out = sum.tanh();
+    }
+}
+
+impl Genome for NeuralGenome {
+    fn generate(rng: &mut impl Rng) -> Self {
+        Self {
+            weights: (0..Self::WEIGHTS)
+                .map(|_| rng.gen_range(-1.0, 1.0))
+                .collect(),
+        }
+    }
+
+    fn mutate(&mut self, rng: &mut impl Rng, config: &MutationConfig, rate: f32) -> usize {
+        let normal = Normal::new(0.0, WEIGHT_MUTATION).unwrap();
+        let count = sample_count(rng, config.point * rate);
+
+        for _ in 0..count {
+            let idx = rng.gen_range(0, self.weights.len());
+            self.weights[idx] += normal.sample(rng);
+        }
+
+        count
+    }
+
+    fn crossover(&self, other: &Self, method: Crossover, rng: &mut impl Rng) -> Self {
+        Self {
+            weights: crossover_slices(&self.weights, &other.weights, method, rng),
+        }
+    }
+
+    fn decide(&self, input: &[BaseType], limits: Limits) -> Result<Decision, RunError> {
+        // one step per weight
+        let steps = self.weights.len();
+        if steps > limits.step_budget {
+            return Err(RunError::StepLimit {
+                pc: 0,
+                steps: limits.step_budget,
+            });
+        }
+
+        let mut inputs = [0.0; INPUTS];
+        for (x, &unit) in inputs.iter_mut().zip(input) {
+            *x = convert_from_unit(unit) * INPUT_SCALE;
+        }
+
+        let mut hidden = [0.0; HIDDEN];
+        let mut outputs = [0.0; OUTPUTS];
+        let (hidden_weights, output_weights) = self.weights.split_at((INPUTS + 1) * HIDDEN);
+        layer(hidden_weights, &inputs, &mut hidden);
+        layer(output_weights, &hidden, &mut outputs);
+
+        Ok(Decision {
+            velocity: Vec2::new(outputs[0], outputs[1]) * MAX_SPEED,
+            actions: Actions {
+                reproduce: outputs[2] > 0.0,
+                eat: outputs[3] > 0.0,
+                attack: outputs[4] > 0.0,
+                rest: outputs[5] > 0.0,
+                signal: ((outputs[6] + 1.0) / 2.0 * MAX_SIGNAL) as BaseType,
+                tick_interval: Some(((outputs[7] + 1.0) / 2.0 * MAX_TICK_INTERVAL) as BaseType),
+            },
+            steps,
+            outputs: OUTPUTS,
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.weights.len()
+    }
+}
+
+#[test]
+fn decide_test() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let genome = NeuralGenome::generate(&mut rng);
+    let decision = genome.decide(&[1, 2, 3], Limits::default()).unwrap();
+
+    assert_eq!(genome.size(), NeuralGenome::WEIGHTS);
+    assert_eq!(decision.steps, NeuralGenome::WEIGHTS);
+    assert!(decision.velocity.length() <= MAX_SPEED * 2.0f32.sqrt());
+
+    let limits = Limits {
+        step_budget: 10,
+        ..Limits::default()
+    };
+    assert!(genome.decide(&[], limits).is_err());
+}
+
+#[test]
+fn weight_count_test() {
+    use super::DNA;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let genome = NeuralGenome::generate(&mut rng);
+    let source = ron::ser::to_string(&genome).unwrap();
+    assert!(ron::de::from_str::<NeuralGenome>(&source).is_ok());
+
+    assert!(ron::de::from_str::<NeuralGenome>("(weights: [0.5, 1.0])").is_err());
+    assert!(ron::de::from_str::<NeuralGenome>("(weights: [])").is_err());
+    assert!(ron::de::from_str::<DNA>("(neural: Some((weights: [0.5])))").is_err());
+}
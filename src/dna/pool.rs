@@ -0,0 +1,239 @@
+use super::{GenomeKind, DNA};
+use crate::config::{InterpreterConfig, PoolConfig};
+use crate::utils::{seed_base, stream_seed};
+
+use crossbeam_channel::Receiver;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+const BATCH: usize = 256;
+/// Rejections in a row after which a DNA is kept anyway,
+/// so a filter nothing passes can't starve the simulation
+const MAX_REJECTIONS: usize = 4096;
+
+/// Counts of DNA generated by a pool
+#[derive(Clone, Copy, Default)]
+pub struct PoolMetrics {
+    pub generated: usize,
+    /// Generated but not viable
+    pub rejected: usize,
+}
+
+impl PoolMetrics {
+    pub fn rejection_rate(&self) -> f32 {
+        if self.generated == 0 {
+            0.0
+        } else {
+            self.rejected as f32 / self.generated as f32
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    generated: AtomicUsize,
+    rejected: AtomicUsize,
+}
+
+/// Generate viable random DNA in background
+///
+/// Each DNA is generated from its own index so the order doesn't depend on thread scheduling
+pub struct DnaPool {
+    rx: Receiver<DNA>,
+    stop: Arc<AtomicBool>,
+    counters: Arc<Counters>,
+    worker: Option<JoinHandle<()>>,
+    kinds: Vec<GenomeKind>,
+    config: PoolConfig,
+    interpreter: InterpreterConfig,
+    input_width: usize,
+}
+
+impl DnaPool {
+    /// DNA are generated with `kinds` in turn and checked on `input_width` input cells
+    pub fn new(
+        seed: u64,
+        kinds: Vec<GenomeKind>,
+        config: PoolConfig,
+        interpreter: InterpreterConfig,
+        input_width: usize,
+    ) -> Self {
+        let (tx, rx) = crossbeam_channel::bounded(config.capacity.max(1));
+        let stop = Arc::new(AtomicBool::new(false));
+        let counters = Arc::new(Counters::default());
+        let threads = ThreadPoolBuilder::new()
+            .num_threads(config.threads)
+            .build()
+            .unwrap();
+
+        let base = seed_base(seed);
+
+        let worker = {
+            let stop = stop.clone();
+            let counters = counters.clone();
+            let kinds = kinds.clone();
+
+            std::thread::spawn(move || {
+                let mut rejections = 0;
+
+                for batch in 0.. {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    let start = batch * BATCH;
+                    let dnas: Vec<(DNA, bool)> = threads.install(|| {
+                        (start..start + BATCH)
+                            .into_par_iter()
+                            .map(|idx| {
+                                let kind = kinds
+                                    .get(idx % kinds.len().max(1))
+                                    .copied()
+                                    .unwrap_or(GenomeKind::Bf);
+                                let dna = DNA::generate(
+                                    kind,
+                                    &mut StdRng::seed_from_u64(stream_seed(base, idx as u64)),
+                                );
+
+                                let viable = !config.filter
+                                    || dna.is_viable(&interpreter, config.min_outputs, input_width);
+
+                                (dna, viable)
+                            })
+                            .collect()
+                    });
+
+                    let rejected = dnas.iter().filter(|(_, viable)| !viable).count();
+                    counters.generated.fetch_add(BATCH, Ordering::Relaxed);
+                    counters.rejected.fetch_add(rejected, Ordering::Relaxed);
+
+                    for (dna, viable) in dnas {
+                        if !viable && rejections + 1 < MAX_REJECTIONS {
+                            rejections += 1;
+                            continue;
+                        }
+
+                        rejections = 0;
+                        if tx.send(dna).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })
+        };
+
+        Self {
+            rx,
+            stop,
+            counters,
+            worker: Some(worker),
+            kinds,
+            config,
+            interpreter,
+            input_width,
+        }
+    }
+
+    /// Restart generation from `seed` with the same settings
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(
+            seed,
+            self.kinds.clone(),
+            self.config,
+            self.interpreter,
+            self.input_width,
+        );
+    }
+
+    /// Next generated DNA, or an unfiltered one from `rng` if the worker stopped
+    pub fn take(&self, rng: &mut impl Rng) -> DNA {
+        match self.rx.recv() {
+            Ok(dna) => dna,
+            Err(_) => DNA::generate(self.kinds.first().copied().unwrap_or(GenomeKind::Bf), rng),
+        }
+    }
+
+    pub fn metrics(&self) -> PoolMetrics {
+        PoolMetrics {
+            generated: self.counters.generated.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for DnaPool {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // disconnect the channel so a blocked send fails
+        self.rx = crossbeam_channel::never();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[test]
+fn pool_test() {
+    let config = PoolConfig {
+        capacity: 4,
+        threads: 2,
+        ..PoolConfig::default()
+    };
+    let interpreter = InterpreterConfig::default();
+    let pool = DnaPool::new(0, vec![GenomeKind::Bf], config, interpreter, 4);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..8 {
+        assert!(pool
+            .take(&mut rng)
+            .is_viable(&interpreter, config.min_outputs, 4));
+    }
+
+    let metrics = pool.metrics();
+    assert!(metrics.generated >= metrics.rejected + 8);
+
+    // must not hang on the full channel
+    drop(pool);
+
+    // nothing is viable, the pool must still deliver
+    let config = PoolConfig {
+        capacity: 1,
+        threads: 1,
+        min_outputs: usize::MAX,
+        ..PoolConfig::default()
+    };
+    let pool = DnaPool::new(0, vec![GenomeKind::Bf], config, interpreter, 4);
+    pool.take(&mut rng);
+    assert!(pool.metrics().rejected >= MAX_REJECTIONS - 1);
+}
+
+#[test]
+fn seed_test() {
+    use std::collections::HashSet;
+
+    let config = PoolConfig {
+        capacity: 16,
+        threads: 2,
+        filter: false,
+        ..PoolConfig::default()
+    };
+    let interpreter = InterpreterConfig::default();
+    let genomes = |seed| {
+        let pool = DnaPool::new(seed, vec![GenomeKind::Bf], config, interpreter, 0);
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..16)
+            .map(|_| pool.take(&mut rng).to_string())
+            .collect::<HashSet<_>>()
+    };
+
+    assert_eq!(genomes(3), genomes(3));
+    assert!(genomes(3) != genomes(4));
+    assert!(genomes(0) != genomes(1));
+}
@@ -0,0 +1,126 @@
+use super::{DnaPool, DNA};
+use crate::config::{MutationConfig, SeedingConfig};
+
+use rand::Rng;
+
+/// Pick DNA of spawned creatures from the sources of `SeedingConfig`
+pub struct Seeder {
+    config: SeedingConfig,
+    library: Vec<DNA>,
+    pool: DnaPool,
+}
+
+impl Seeder {
+    pub fn new(config: SeedingConfig, library: Vec<DNA>, pool: DnaPool) -> Self {
+        Self {
+            config,
+            library,
+            pool,
+        }
+    }
+
+    pub fn pool(&self) -> &DnaPool {
+        &self.pool
+    }
+
+    pub fn pool_mut(&mut self) -> &mut DnaPool {
+        &mut self.pool
+    }
+
+    /// Number of fittest creatures to pass to `take`, 0 when mutants are never picked
+    pub fn elite_count(&self) -> usize {
+        if self.config.elite > 0.0 {
+            self.config.elite_count
+        } else {
+            0
+        }
+    }
+
+    /// DNA of a new creature, `elites` are the DNA of the fittest living creatures
+    pub fn take(&self, rng: &mut impl Rng, elites: &[DNA], mutation: &MutationConfig) -> DNA {
+        match self.pick(rng, elites.len()) {
+            Source::Random => self.pool.take(rng),
+            Source::Library(idx) => self.library[idx].clone(),
+            Source::Elite(idx) => elites[idx].duplicate(rng, mutation),
+        }
+    }
+
+    fn pick(&self, rng: &mut impl Rng, elite_count: usize) -> Source {
+        let weight = |ratio: f32, count: usize| if count == 0 { 0.0 } else { ratio.max(0.0) };
+        let random = self.config.random.max(0.0);
+        let library = weight(self.config.library, self.library.len());
+        let elite = weight(self.config.elite, elite_count);
+
+        // random only runs don't draw from the rng, so earlier seeds still reproduce
+        if library + elite <= 0.0 {
+            return Source::Random;
+        }
+
+        let mut ratio = rng.gen_range(0.0, random + library + elite);
+        if ratio < random {
+            return Source::Random;
+        }
+
+        ratio -= random;
+        if ratio < library {
+            Source::Library(rng.gen_range(0, self.library.len()))
+        } else {
+            Source::Elite(rng.gen_range(0, elite_count))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Source {
+    Random,
+    Library(usize),
+    Elite(usize),
+}
+
+#[test]
+fn pick_test() {
+    use super::GenomeKind;
+    use crate::config::{InterpreterConfig, PoolConfig};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let pool = || {
+        DnaPool::new(
+            0,
+            vec![GenomeKind::Bf],
+            PoolConfig {
+                capacity: 1,
+                threads: 1,
+                ..PoolConfig::default()
+            },
+            InterpreterConfig::default(),
+            0,
+        )
+    };
+    let library = vec![DNA::generate(GenomeKind::Bf, &mut rng)];
+    let config = SeedingConfig {
+        random: 0.0,
+        library: 1.0,
+        elite: 1.0,
+        elite_count: 3,
+    };
+
+    let seeder = Seeder::new(SeedingConfig::default(), library.clone(), pool());
+    assert_eq!(seeder.pick(&mut rng, 3), Source::Random);
+    assert_eq!(seeder.elite_count(), 0);
+
+    let seeder = Seeder::new(config, Vec::new(), pool());
+    for _ in 0..10 {
+        assert!(matches!(seeder.pick(&mut rng, 3), Source::Elite(idx) if idx < 3));
+    }
+    assert_eq!(seeder.pick(&mut rng, 0), Source::Random);
+
+    let seeder = Seeder::new(config, library, pool());
+    let picks: Vec<_> = (0..100).map(|_| seeder.pick(&mut rng, 3)).collect();
+    assert!(picks.contains(&Source::Library(0)));
+    assert!(picks
+        .iter()
+        .any(|source| matches!(source, Source::Elite(_))));
+    assert!(!picks.contains(&Source::Random));
+}
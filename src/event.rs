@@ -0,0 +1,19 @@
+use crate::component::DeathCause;
+use crate::hall_of_fame::CreatureRecord;
+
+/// Sent when a creature is despawned
+pub struct CreatureDied {
+    pub cause: DeathCause,
+    pub record: CreatureRecord,
+}
+
+/// Sent when a creature gets its ID
+pub struct CreatureBorn {
+    /// No parent when seeded
+    pub parents: usize,
+    /// Mutations applied to the DNA inherited from parents
+    pub mutations: usize,
+}
+
+/// Sent when a creature eats a food
+pub struct FoodEaten;
@@ -0,0 +1,418 @@
+use crate::config::{
+    ActionConfig, GenomeConfig, HallOfFameConfig, InterpreterConfig, MatingConfig,
+    MetabolismConfig, MutationConfig, PhylogenyConfig, PoolConfig, SeedingConfig, SensorConfig,
+    SimulationConfig, StatsConfig, WorldConfig,
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Every parameter of a run, loaded from a RON file
+///
+/// Missing sections and fields keep their default
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExperimentConfig {
+    pub simulation: SimulationConfig,
+    pub world: WorldConfig,
+    pub interpreter: InterpreterConfig,
+    pub metabolism: MetabolismConfig,
+    pub sensor: SensorConfig,
+    pub action: ActionConfig,
+    pub mating: MatingConfig,
+    pub mutation: MutationConfig,
+    pub genome: GenomeConfig,
+    pub pool: PoolConfig,
+    pub seeding: SeedingConfig,
+    pub hall_of_fame: HallOfFameConfig,
+    pub phylogeny: PhylogenyConfig,
+    pub stats: StatsConfig,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Format(ron::Error),
+    /// Field path and why its value is rejected
+    Invalid(&'static str, &'static str),
+    /// Field path not known by `ExperimentConfig::set`
+    UnknownParameter(String),
+    /// Field path and the value `ExperimentConfig::set` can't store in an integer field
+    InvalidCount(String, f64),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "io error: {}", err),
+            ConfigError::Format(err) => write!(f, "format error: {}", err),
+            ConfigError::Invalid(field, reason) => write!(f, "invalid {}: {}", field, reason),
+            ConfigError::UnknownParameter(name) => write!(f, "unknown parameter: {}", name),
+            ConfigError::InvalidCount(name, value) => {
+                write!(
+                    f,
+                    "invalid {}: {} is not a non-negative number",
+                    name, value
+                )
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<ron::Error> for ConfigError {
+    fn from(err: ron::Error) -> Self {
+        ConfigError::Format(err)
+    }
+}
+
+fn check(valid: bool, field: &'static str, reason: &'static str) -> Result<(), ConfigError> {
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(field, reason))
+    }
+}
+
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+fn is_non_negative(value: f32) -> bool {
+    value.is_finite() && value >= 0.0
+}
+
+fn fits(object: (usize, usize), world: (usize, usize)) -> bool {
+    object.0 > 0 && object.1 > 0 && object.0 <= world.0 / 2 && object.1 <= world.1 / 2
+}
+
+impl ExperimentConfig {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = ron::de::from_str(&fs::read_to_string(path)?)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Set the numeric field at path `name`, rounding `value` for integer fields
+    ///
+    /// Integer fields reject negative and non-finite values, float fields are left to `validate`
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(), ConfigError> {
+        let real = value as f32;
+        let count = || {
+            if value.is_finite() && value >= 0.0 {
+                Ok(value.round() as usize)
+            } else {
+                Err(ConfigError::InvalidCount(name.to_owned(), value))
+            }
+        };
+
+        match name {
+            "simulation.daily_creature_count" => self.simulation.daily_creature_count = count()?,
+            "simulation.daily_food_count" => self.simulation.daily_food_count = count()?,
+            "simulation.turn_interval" => self.simulation.turn_interval = real,
+            "world.scale" => self.world.scale = real,
+            "interpreter.step_budget" => self.interpreter.step_budget = count()?,
+            "interpreter.tape_size" => self.interpreter.tape_size = count()?,
+            "metabolism.base" => self.metabolism.base = real,
+            "metabolism.per_instruction" => self.metabolism.per_instruction = real,
+            "metabolism.per_step" => self.metabolism.per_step = real,
+            "metabolism.per_speed" => self.metabolism.per_speed = real,
+            "metabolism.resting" => self.metabolism.resting = real,
+            "metabolism.food_energy" => self.metabolism.food_energy = real,
+            "metabolism.reproduction_cost" => self.metabolism.reproduction_cost = real,
+            "metabolism.move_exponent" => self.metabolism.move_exponent = real,
+            "metabolism.move_divisor" => self.metabolism.move_divisor = real,
+            "action.attack_damage" => self.action.attack_damage = real,
+            "action.tick_interval_unit" => self.action.tick_interval_unit = real,
+            "action.min_tick_interval" => self.action.min_tick_interval = real,
+            "action.max_tick_interval" => self.action.max_tick_interval = real,
+            "action.init_tick_interval" => self.action.init_tick_interval = real,
+            "mating.sexual_ratio" => self.mating.sexual_ratio = real,
+            "mating.cost" => self.mating.cost = real,
+            "mutation.point" => self.mutation.point = real,
+            "mutation.insertion" => self.mutation.insertion = real,
+            "mutation.deletion" => self.mutation.deletion = real,
+            "mutation.duplication" => self.mutation.duplication = real,
+            "mutation.inversion" => self.mutation.inversion = real,
+            "mutation.transposition" => self.mutation.transposition = real,
+            "mutation.max_segment" => self.mutation.max_segment = count()?,
+            "mutation.rate" => self.mutation.rate = real,
+            "mutation.rate_adaptation" => self.mutation.rate_adaptation = real,
+            "seeding.random" => self.seeding.random = real,
+            "seeding.library" => self.seeding.library = real,
+            "seeding.elite" => self.seeding.elite = real,
+            "seeding.elite_count" => self.seeding.elite_count = count()?,
+            _ => return Err(ConfigError::UnknownParameter(name.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Reject values the simulation can't run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let positive = "must be finite and positive";
+        let non_negative = "must be finite and not negative";
+
+        let simulation = &self.simulation;
+        check(
+            is_positive(simulation.turn_interval),
+            "simulation.turn_interval",
+            positive,
+        )?;
+
+        let world = &self.world;
+        check(is_positive(world.scale), "world.scale", positive)?;
+        check(
+            fits(world.creature, world.size),
+            "world.creature",
+            "must be positive and fit in half of world.size",
+        )?;
+        check(
+            fits(world.food, world.size),
+            "world.food",
+            "must be positive and fit in half of world.size",
+        )?;
+
+        let interpreter = &self.interpreter;
+        check(
+            interpreter.step_budget > 0,
+            "interpreter.step_budget",
+            positive,
+        )?;
+        check(interpreter.tape_size > 0, "interpreter.tape_size", positive)?;
+
+        let metabolism = &self.metabolism;
+        for &(value, field) in [
+            (metabolism.base, "metabolism.base"),
+            (metabolism.per_instruction, "metabolism.per_instruction"),
+            (metabolism.per_step, "metabolism.per_step"),
+            (metabolism.per_speed, "metabolism.per_speed"),
+            (metabolism.resting, "metabolism.resting"),
+            (metabolism.food_energy, "metabolism.food_energy"),
+            (metabolism.reproduction_cost, "metabolism.reproduction_cost"),
+        ]
+        .iter()
+        {
+            check(is_non_negative(value), field, non_negative)?;
+        }
+        check(
+            is_positive(metabolism.move_exponent),
+            "metabolism.move_exponent",
+            positive,
+        )?;
+        check(
+            is_positive(metabolism.move_divisor),
+            "metabolism.move_divisor",
+            positive,
+        )?;
+
+        let action = &self.action;
+        check(
+            is_non_negative(action.attack_damage),
+            "action.attack_damage",
+            non_negative,
+        )?;
+        check(
+            is_positive(action.tick_interval_unit),
+            "action.tick_interval_unit",
+            positive,
+        )?;
+        check(
+            is_positive(action.min_tick_interval),
+            "action.min_tick_interval",
+            positive,
+        )?;
+        check(
+            action.max_tick_interval.is_finite()
+                && action.max_tick_interval >= action.min_tick_interval,
+            "action.max_tick_interval",
+            "must be finite and not less than action.min_tick_interval",
+        )?;
+        check(
+            is_positive(action.init_tick_interval),
+            "action.init_tick_interval",
+            positive,
+        )?;
+
+        let mating = &self.mating;
+        check(
+            (0.0..=1.0).contains(&mating.sexual_ratio),
+            "mating.sexual_ratio",
+            "must be between 0 and 1",
+        )?;
+        check(is_non_negative(mating.cost), "mating.cost", non_negative)?;
+
+        let mutation = &self.mutation;
+        for &(value, field) in [
+            (mutation.point, "mutation.point"),
+            (mutation.insertion, "mutation.insertion"),
+            (mutation.deletion, "mutation.deletion"),
+            (mutation.duplication, "mutation.duplication"),
+            (mutation.inversion, "mutation.inversion"),
+            (mutation.transposition, "mutation.transposition"),
+            (mutation.rate, "mutation.rate"),
+        ]
+        .iter()
+        {
+            check(is_non_negative(value), field, non_negative)?;
+        }
+        check(
+            mutation.rate_adaptation.is_finite(),
+            "mutation.rate_adaptation",
+            "must be finite",
+        )?;
+        check(mutation.max_segment > 0, "mutation.max_segment", positive)?;
+        if let (Some(min), Some(max)) = (mutation.min_length, mutation.max_length) {
+            check(
+                min <= max,
+                "mutation.max_length",
+                "must not be less than mutation.min_length",
+            )?;
+        }
+
+        check(
+            !self.genome.kinds.is_empty(),
+            "genome.kinds",
+            "must not be empty",
+        )?;
+
+        let pool = &self.pool;
+        // the channel allocates every slot up front
+        check(
+            (1..=1 << 16).contains(&pool.capacity),
+            "pool.capacity",
+            "must be between 1 and 65536",
+        )?;
+        // each output cell takes at least a step
+        check(
+            !pool.filter || pool.min_outputs <= interpreter.step_budget,
+            "pool.min_outputs",
+            "must not exceed interpreter.step_budget",
+        )?;
+
+        let seeding = &self.seeding;
+        for &(value, field) in [
+            (seeding.random, "seeding.random"),
+            (seeding.library, "seeding.library"),
+            (seeding.elite, "seeding.elite"),
+        ]
+        .iter()
+        {
+            check(is_non_negative(value), field, non_negative)?;
+        }
+        check(
+            seeding.random + seeding.library + seeding.elite > 0.0,
+            "seeding",
+            "ratios must not all be zero",
+        )?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn parse_test() {
+    let config: ExperimentConfig = ron::de::from_str(
+        "(
+            simulation: (daily_food_count: 10),
+            world: (size: (2000, 1000)),
+            metabolism: (food_energy: 5.0),
+            genome: (kinds: [Bf, Neural]),
+            hall_of_fame: (size: 5),
+            stats: (path: Some(\"stats.csv\")),
+        )",
+    )
+    .unwrap();
+
+    assert_eq!(config.simulation.daily_food_count, 10);
+    assert_eq!(config.simulation.daily_creature_count, 1000);
+    assert_eq!(config.world.size, (2000, 1000));
+    assert_eq!(config.world.creature, (40, 40));
+    assert_eq!(config.metabolism.food_energy, 5.0);
+    assert_eq!(config.genome.kinds.len(), 2);
+    assert_eq!(config.hall_of_fame.size, 5);
+    assert_eq!(config.hall_of_fame.save_interval, 100);
+    assert!(config.stats.path.is_some());
+    assert!(config.validate().is_ok());
+
+    assert!(ron::de::from_str::<ExperimentConfig>("(wrold: ())").is_err());
+    assert!(ExperimentConfig::default().validate().is_ok());
+}
+
+#[test]
+fn validate_test() {
+    let invalid_field = |config: ExperimentConfig| match config.validate() {
+        Err(ConfigError::Invalid(field, _)) => field,
+        _ => panic!("config should be invalid"),
+    };
+
+    let mut config = ExperimentConfig::default();
+    config.world.size = (60, 60);
+    assert_eq!(invalid_field(config), "world.creature");
+
+    let mut config = ExperimentConfig::default();
+    config.action.max_tick_interval = 0.01;
+    assert_eq!(invalid_field(config), "action.max_tick_interval");
+
+    let mut config = ExperimentConfig::default();
+    config.mating.sexual_ratio = 1.5;
+    assert_eq!(invalid_field(config), "mating.sexual_ratio");
+
+    let mut config = ExperimentConfig::default();
+    config.mutation.rate_adaptation = f32::NAN;
+    assert_eq!(invalid_field(config), "mutation.rate_adaptation");
+
+    let mut config = ExperimentConfig::default();
+    config
+        .set("mutation.rate_adaptation", f64::INFINITY)
+        .unwrap();
+    assert_eq!(invalid_field(config), "mutation.rate_adaptation");
+
+    let mut config = ExperimentConfig::default();
+    config.world.scale = f32::INFINITY;
+    assert_eq!(invalid_field(config), "world.scale");
+
+    let mut config = ExperimentConfig::default();
+    config.set("metabolism.food_energy", f64::NAN).unwrap();
+    assert_eq!(invalid_field(config), "metabolism.food_energy");
+
+    let mut config = ExperimentConfig::default();
+    config.seeding.random = 0.0;
+    config.seeding.library = 0.0;
+    config.seeding.elite = 0.0;
+    assert_eq!(invalid_field(config), "seeding");
+
+    let mut config = ExperimentConfig::default();
+    for &value in [-1.0, f64::NAN, f64::INFINITY].iter() {
+        assert!(matches!(
+            config.set("mutation.max_segment", value),
+            Err(ConfigError::InvalidCount(..))
+        ));
+    }
+    config.set("mutation.max_segment", 2.6).unwrap();
+    assert_eq!(config.mutation.max_segment, 3);
+
+    let mut config = ExperimentConfig::default();
+    config.genome.kinds.clear();
+    assert_eq!(invalid_field(config), "genome.kinds");
+
+    let mut config = ExperimentConfig::default();
+    config.pool.capacity = 0;
+    assert_eq!(invalid_field(config), "pool.capacity");
+
+    let mut config = ExperimentConfig::default();
+    config.pool.min_outputs = config.interpreter.step_budget + 1;
+    assert_eq!(invalid_field(config.clone()), "pool.min_outputs");
+    config.pool.filter = false;
+    assert!(config.validate().is_ok());
+}
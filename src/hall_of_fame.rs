@@ -0,0 +1,156 @@
+use crate::dna::DNA;
+use crate::snapshot::SnapshotError;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+/// Life of a creature, recorded when it despawns or when the app exits
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CreatureRecord {
+    pub id: Option<u64>,
+    pub generation: usize,
+    /// Turns lived
+    pub old: usize,
+    pub food_eaten: usize,
+    pub offspring: usize,
+    pub dna: DNA,
+}
+
+/// What makes a creature notable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    LongestLived,
+    MostOffspring,
+    DeepestGeneration,
+}
+
+impl Category {
+    pub const ALL: [Category; 3] = [
+        Category::LongestLived,
+        Category::MostOffspring,
+        Category::DeepestGeneration,
+    ];
+
+    pub fn score(self, record: &CreatureRecord) -> usize {
+        match self {
+            Category::LongestLived => record.old,
+            Category::MostOffspring => record.offspring,
+            Category::DeepestGeneration => record.generation,
+        }
+    }
+}
+
+/// Best creatures of the run in each category, best first
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HallOfFame {
+    pub longest_lived: Vec<CreatureRecord>,
+    pub most_offspring: Vec<CreatureRecord>,
+    pub deepest_generation: Vec<CreatureRecord>,
+    /// Changed since the last save
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+impl HallOfFame {
+    pub fn entries(&self, category: Category) -> &[CreatureRecord] {
+        match category {
+            Category::LongestLived => &self.longest_lived,
+            Category::MostOffspring => &self.most_offspring,
+            Category::DeepestGeneration => &self.deepest_generation,
+        }
+    }
+
+    fn entries_mut(&mut self, category: Category) -> &mut Vec<CreatureRecord> {
+        match category {
+            Category::LongestLived => &mut self.longest_lived,
+            Category::MostOffspring => &mut self.most_offspring,
+            Category::DeepestGeneration => &mut self.deepest_generation,
+        }
+    }
+
+    /// Keep `record` in each category it is among the `size` best, earlier records win ties
+    pub fn record(&mut self, record: &CreatureRecord, size: usize) {
+        for &category in &Category::ALL {
+            let score = category.score(record);
+            let entries = self.entries_mut(category);
+            let idx = entries
+                .iter()
+                .position(|entry| category.score(entry) < score)
+                .unwrap_or(entries.len());
+
+            if idx < size {
+                entries.insert(idx, record.clone());
+                entries.truncate(size);
+                self.unsaved = true;
+            }
+        }
+    }
+
+    pub fn is_unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    /// DNA of every entry, entries notable in several categories are repeated
+    pub fn genomes(&self) -> impl Iterator<Item = &DNA> {
+        Category::ALL
+            .iter()
+            .flat_map(move |&category| self.entries(category))
+            .map(|record| &record.dna)
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), SnapshotError> {
+        let source = ron::ser::to_string_pretty(self, Default::default())?;
+        fs::write(path, source)?;
+        self.unsaved = false;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let file = BufReader::new(File::open(path)?);
+
+        Ok(ron::de::from_reader(file)?)
+    }
+}
+
+#[test]
+fn record_test() {
+    use crate::dna::GenomeKind;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut record = |old, offspring| CreatureRecord {
+        id: None,
+        generation: 0,
+        old,
+        food_eaten: 0,
+        offspring,
+        dna: DNA::generate(GenomeKind::Bf, &mut rng),
+    };
+    let scores = |hall_of_fame: &HallOfFame, category: Category| {
+        hall_of_fame
+            .entries(category)
+            .iter()
+            .map(|record| category.score(record))
+            .collect::<Vec<_>>()
+    };
+
+    let mut hall_of_fame = HallOfFame::default();
+    for (old, offspring) in [(3, 0), (5, 1), (1, 4), (4, 2)].iter() {
+        hall_of_fame.record(&record(*old, *offspring), 2);
+    }
+
+    assert_eq!(scores(&hall_of_fame, Category::LongestLived), vec![5, 4]);
+    assert_eq!(scores(&hall_of_fame, Category::MostOffspring), vec![4, 2]);
+    assert_eq!(
+        scores(&hall_of_fame, Category::DeepestGeneration),
+        vec![0, 0]
+    );
+    assert_eq!(hall_of_fame.entries(Category::DeepestGeneration)[0].old, 3);
+    assert_eq!(hall_of_fame.genomes().count(), 6);
+    assert!(hall_of_fame.is_unsaved());
+}
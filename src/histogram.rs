@@ -0,0 +1,133 @@
+use std::fmt::Write;
+
+/// Counts of values in contiguous inclusive ranges
+pub struct Histogram {
+    /// Lowest value, highest value and count of each bin
+    bins: Vec<(usize, usize, usize)>,
+}
+
+impl Histogram {
+    /// At most `max_bins` bins of equal width covering the observed range
+    pub fn linear(values: &[usize], max_bins: usize) -> Self {
+        let (min, max) = match (values.iter().min(), values.iter().max()) {
+            (Some(&min), Some(&max)) => (min, max),
+            _ => return Self { bins: Vec::new() },
+        };
+
+        // `max - min` instead of the range length, which overflows for a full range
+        let width = ((max - min) / max_bins.max(1)).saturating_add(1);
+        let mut bins: Vec<_> = (0..=(max - min) / width)
+            .map(|idx| {
+                let low = min + idx * width;
+                (low, low.saturating_add(width - 1).min(max), 0)
+            })
+            .collect();
+
+        for &value in values {
+            bins[(value - min) / width].2 += 1;
+        }
+
+        Self { bins }
+    }
+
+    /// Bins 0, 1, 2-3, 4-7, ... doubling up to the highest value
+    pub fn log2(values: &[usize]) -> Self {
+        let bin = |value: usize| (0usize.leading_zeros() - value.leading_zeros()) as usize;
+        let max = match values.iter().max() {
+            Some(&max) => max,
+            None => return Self { bins: Vec::new() },
+        };
+
+        let mut bins: Vec<_> = (0..=bin(max))
+            .map(|idx| match idx {
+                0 => (0, 0, 0),
+                _ => {
+                    let low = 1 << (idx - 1);
+                    (low, low - 1 + low, 0)
+                }
+            })
+            .collect();
+
+        for &value in values {
+            bins[bin(value)].2 += 1;
+        }
+
+        Self { bins }
+    }
+
+    pub fn bins(&self) -> &[(usize, usize, usize)] {
+        &self.bins
+    }
+
+    /// One line per bin with its range, a bar up to `width` long and its count
+    pub fn bar_chart(&self, width: usize) -> String {
+        let max_count = self.bins.iter().map(|&(_, _, count)| count).max();
+        let labels: Vec<_> = self
+            .bins
+            .iter()
+            .map(|&(low, high, _)| {
+                if low == high {
+                    low.to_string()
+                } else {
+                    format!("{}-{}", low, high)
+                }
+            })
+            .collect();
+        let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+
+        let mut chart = String::new();
+        for (label, &(_, _, count)) in labels.iter().zip(&self.bins) {
+            // non-empty bins always get a bar
+            let len = match max_count {
+                Some(max_count) if count > 0 => (count * width / max_count).max(1),
+                _ => 0,
+            };
+            writeln!(
+                chart,
+                "{:>label_width$} {} {}",
+                label,
+                "█".repeat(len),
+                count,
+                label_width = label_width
+            )
+            .unwrap();
+        }
+
+        chart
+    }
+}
+
+#[test]
+fn linear_test() {
+    let histogram = Histogram::linear(&[3, 4, 5, 12, 30], 3);
+    assert_eq!(histogram.bins(), &[(3, 12, 4), (13, 22, 0), (23, 30, 1)]);
+
+    let histogram = Histogram::linear(&[7, 7], 10);
+    assert_eq!(histogram.bins(), &[(7, 7, 2)]);
+
+    let histogram = Histogram::linear(&[0, usize::MAX], 4);
+    assert_eq!(histogram.bins().len(), 4);
+    assert_eq!(histogram.bins()[3].1, usize::MAX);
+
+    assert!(Histogram::linear(&[], 10).bins().is_empty());
+    assert_eq!(Histogram::linear(&[1, 2], 0).bins(), &[(1, 2, 2)]);
+}
+
+#[test]
+fn log2_test() {
+    let histogram = Histogram::log2(&[0, 1, 2, 3, 4, 9, usize::MAX]);
+    assert_eq!(
+        histogram.bins()[..5],
+        [(0, 0, 1), (1, 1, 1), (2, 3, 2), (4, 7, 1), (8, 15, 1)]
+    );
+    assert_eq!(histogram.bins().last(), Some(&(1 << 63, usize::MAX, 1)));
+
+    assert!(Histogram::log2(&[]).bins().is_empty());
+}
+
+#[test]
+fn bar_chart_test() {
+    let chart = Histogram::linear(&[1, 1, 1, 1, 2, 10], 2).bar_chart(4);
+    assert_eq!(chart, " 1-5 ████ 5\n6-10 █ 1\n");
+    assert_eq!(Histogram::linear(&[], 2).bar_chart(4), "");
+}
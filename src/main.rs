@@ -1,18 +1,217 @@
 mod dna;
 
+mod cli;
 mod component;
+mod config;
 mod constants;
+mod event;
+mod experiment;
+mod hall_of_fame;
+mod histogram;
+mod phylogeny;
 mod resource;
+mod sensor;
+mod snapshot;
+mod stats;
+mod sweep;
 mod system;
 mod utils;
 
+use crate::cli::Options;
+use crate::config::GenomeConfig;
+use crate::dna::DNA;
+use crate::experiment::ExperimentConfig;
+use crate::resource::SimulationClock;
+use crate::snapshot::Library;
+use crate::stats::TurnStats;
+use crate::sweep::{Run, SweepConfig};
 use crate::system::NaturalSelectionPlugin;
 
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::core::{CorePlugin, DefaultTaskPoolOptions};
 use bevy::prelude::{AddDefaultPlugins, App};
+use bevy::type_registry::TypeRegistryPlugin;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+fn load_program(path: &Path) -> Result<DNA, Box<dyn Error>> {
+    Ok(fs::read_to_string(path)?.parse()?)
+}
+
+/// Simulate `run` headless for `turns` turns and return its totals
+fn run_headless(run: &Run, turns: usize) -> TurnStats {
+    let summary = Arc::new(Mutex::new(None));
+    let mut experiment = run.experiment.clone();
+    // concurrent runs would write over each other, the sweep summary is the only output
+    experiment.hall_of_fame.path = None;
+    experiment.phylogeny.path = None;
+    experiment.stats.path = None;
+    // runs already fill the cores
+    experiment.pool.threads = 1;
+
+    let simulation = experiment.simulation;
+    let plugin = NaturalSelectionPlugin::new(
+        simulation.daily_creature_count,
+        simulation.daily_food_count,
+        simulation.turn_interval,
+    )
+    .experiment(experiment)
+    .headless(true)
+    .seed(run.seed)
+    .steps_per_frame(SimulationClock::MAX_STEPS_PER_FRAME)
+    .turn_limit(turns, summary.clone());
 
-fn main() {
     App::build()
-        .add_default_plugins()
-        .add_plugin(NaturalSelectionPlugin::new(1000, 150, 0.5))
+        .add_resource(DefaultTaskPoolOptions::with_num_threads(1))
+        .add_plugin(TypeRegistryPlugin::default())
+        .add_plugin(CorePlugin::default())
+        .add_plugin(ScheduleRunnerPlugin::default())
+        .add_plugin(plugin)
         .run();
+
+    let summary = summary.lock().unwrap().take();
+    summary.unwrap_or_default()
+}
+
+/// Run every run of `sweep` in parallel, writing summary rows in run order as they end
+fn run_sweep(sweep: &SweepConfig) -> Result<(), Box<dyn Error>> {
+    let runs = sweep.runs()?;
+    let threads = ThreadPoolBuilder::new()
+        .num_threads(sweep.threads)
+        .build()?;
+
+    let mut writer = BufWriter::new(File::create(&sweep.output)?);
+    writer.write_all(sweep.header().as_bytes())?;
+    writer.flush()?;
+    // the writer, the next row to write and the rows of runs ended before it
+    let rows = Mutex::new((writer, 0, BTreeMap::new()));
+
+    println!(
+        "Sweep: {} runs of {} turns to {}",
+        runs.len(),
+        sweep.turns,
+        sweep.output.display()
+    );
+
+    threads.install(|| {
+        runs.par_iter().try_for_each(|run| -> io::Result<()> {
+            let stats = run_headless(run, sweep.turns);
+            let mut rows = rows.lock().unwrap();
+            let (writer, next, pending) = &mut *rows;
+            pending.insert(run.index, sweep.row(run, &stats));
+            while let Some(row) = pending.remove(next) {
+                writer.write_all(row.as_bytes())?;
+                *next += 1;
+            }
+            writer.flush()?;
+            println!("Run {} (seed {}) finished", run.index, run.seed);
+
+            Ok(())
+        })
+    })?;
+
+    Ok(())
+}
+
+fn main() {
+    let options = Options::from_args();
+
+    if let Some(path) = &options.sweep {
+        let sweep = SweepConfig::load(path).unwrap_or_else(|err| {
+            eprintln!("Can't load sweep {}: {}", path.display(), err);
+            std::process::exit(2);
+        });
+
+        if let Err(err) = run_sweep(&sweep) {
+            eprintln!("Sweep failed: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    let mut app = App::build();
+
+    if options.headless {
+        app.add_plugin(TypeRegistryPlugin::default())
+            .add_plugin(CorePlugin::default())
+            .add_plugin(ScheduleRunnerPlugin::default());
+    } else {
+        app.add_default_plugins();
+    }
+
+    let programs = options
+        .programs
+        .iter()
+        .map(|path| {
+            load_program(path).unwrap_or_else(|err| {
+                eprintln!("Can't load program {}: {}", path.display(), err);
+                std::process::exit(2);
+            })
+        })
+        .collect();
+
+    let seed = options.seed.unwrap_or_else(rand::random);
+    println!("Seed: {}", seed);
+
+    let mut experiment = match &options.config {
+        Some(path) => ExperimentConfig::load(path).unwrap_or_else(|err| {
+            eprintln!("Can't load config {}: {}", path.display(), err);
+            std::process::exit(2);
+        }),
+        None => ExperimentConfig::default(),
+    };
+    if options.hall_of_fame.is_some() {
+        experiment.hall_of_fame.path = options.hall_of_fame;
+    }
+    if options.phylogeny.is_some() {
+        experiment.phylogeny.path = options.phylogeny;
+    }
+    if options.stats.is_some() {
+        experiment.stats.path = options.stats;
+    }
+    let simulation = experiment.simulation;
+
+    let mut plugin = NaturalSelectionPlugin::new(
+        simulation.daily_creature_count,
+        simulation.daily_food_count,
+        simulation.turn_interval,
+    )
+    .experiment(experiment)
+    .headless(options.headless)
+    .seed(seed)
+    .steps_per_frame(options.speed)
+    .snapshot(options.snapshot, options.autosave, options.load)
+    .programs(programs);
+
+    if !options.genomes.is_empty() {
+        plugin = plugin.genome_config(GenomeConfig {
+            kinds: options.genomes,
+        });
+    }
+
+    if let Some(path) = &options.library {
+        let library = Library::load(path).unwrap_or_else(|err| {
+            eprintln!("Can't load library {}: {}", path.display(), err);
+            std::process::exit(2);
+        });
+        plugin = plugin.library(library.genomes);
+    }
+
+    if let Some(config) = options.seeding {
+        plugin = plugin.seeding_config(config);
+    }
+
+    if let Some(turns) = options.turns {
+        plugin = plugin.turn_limit(turns, Arc::default());
+    }
+
+    app.add_plugin(plugin).run();
 }
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Birth of a creature in the lineage tree
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Node {
+    pub id: u64,
+    /// One parent when duplicated, two when mated, none when seeded
+    pub parents: Vec<u64>,
+    pub birth_turn: usize,
+    pub death_turn: Option<usize>,
+}
+
+/// Node ID found more than once in a lineage
+#[derive(Debug)]
+pub struct DuplicateId(pub u64);
+
+impl fmt::Display for DuplicateId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "duplicate node id {}", self.0)
+    }
+}
+
+/// Registered creatures, kept after despawn until pruned, ordered by ID
+///
+/// Offspring may be inserted before their parents, like creatures loaded from a snapshot
+#[derive(Default)]
+pub struct Phylogeny {
+    nodes: BTreeMap<u64, Node>,
+    /// Children of each ID, counted even before the parent is inserted
+    children: HashMap<u64, usize>,
+    next_id: u64,
+}
+
+impl Phylogeny {
+    pub fn from_nodes(nodes: Vec<Node>) -> Result<Self, DuplicateId> {
+        let mut phylogeny = Self::default();
+        for node in nodes {
+            if phylogeny.contains(node.id) {
+                return Err(DuplicateId(node.id));
+            }
+            phylogeny.insert(node);
+        }
+        Ok(phylogeny)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.values()
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    /// Give a new ID to a born creature
+    pub fn register(&mut self, parents: Vec<u64>, birth_turn: usize) -> u64 {
+        let id = self.next_id;
+        self.insert(Node {
+            id,
+            parents,
+            birth_turn,
+            death_turn: None,
+        });
+        id
+    }
+
+    /// Add a creature which already has an ID, like one loaded from a snapshot
+    pub fn insert(&mut self, node: Node) {
+        self.next_id = self.next_id.max(node.id + 1);
+        for &parent in &node.parents {
+            *self.children.entry(parent).or_insert(0) += 1;
+        }
+        self.nodes.insert(node.id, node);
+    }
+
+    pub fn record_death(&mut self, id: u64, turn: usize) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.death_turn = Some(turn);
+        }
+    }
+
+    /// Remove `id` if it died without living descendants, then its ancestors left the same
+    pub fn prune(&mut self, id: u64) {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let extinct = match self.nodes.get(&id) {
+                Some(node) => node.death_turn.is_some() && !self.children.contains_key(&id),
+                None => false,
+            };
+            if !extinct {
+                continue;
+            }
+
+            let node = self.nodes.remove(&id).unwrap();
+            for parent in node.parents {
+                let count = self.children.get_mut(&parent).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.children.remove(&parent);
+                    stack.push(parent);
+                }
+            }
+        }
+    }
+
+    /// ID of the parent a node hangs from in a tree, the first registered one
+    fn tree_parent(&self, node: &Node) -> Option<u64> {
+        node.parents
+            .iter()
+            .copied()
+            .find(|&parent| self.contains(parent))
+    }
+
+    /// One Newick tree per line for each creature without a registered parent
+    ///
+    /// Mated creatures hang from their first parent, branch lengths are turns between births
+    pub fn to_newick(&self) -> String {
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        let mut roots = Vec::new();
+
+        for node in self.nodes.values() {
+            match self.tree_parent(node) {
+                Some(parent) => children.entry(parent).or_default().push(node.id),
+                None => roots.push(node.id),
+            }
+        }
+
+        // depth first without recursion, each entry is a node and its next child
+        let mut newick = String::new();
+        let no_children = Vec::new();
+
+        for root in roots {
+            let mut stack = vec![(root, 0)];
+
+            while let Some((id, next)) = stack.pop() {
+                let node_children = children.get(&id).unwrap_or(&no_children);
+                if next < node_children.len() {
+                    newick.push(if next == 0 { '(' } else { ',' });
+                    stack.push((id, next + 1));
+                    stack.push((node_children[next], 0));
+                    continue;
+                }
+
+                if !node_children.is_empty() {
+                    newick.push(')');
+                }
+
+                let node = &self.nodes[&id];
+                write!(newick, "{}", node.id).unwrap();
+                if let Some(parent) = self.tree_parent(node) {
+                    let length = node
+                        .birth_turn
+                        .saturating_sub(self.nodes[&parent].birth_turn);
+                    write!(newick, ":{}", length).unwrap();
+                }
+            }
+
+            newick.push_str(";\n");
+        }
+
+        newick
+    }
+
+    /// Edge list with one row per parent, creatures without parent have an empty parent
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("parent,child,birth_turn,death_turn\n");
+
+        for node in self.nodes.values() {
+            let death_turn = node
+                .death_turn
+                .map_or(String::new(), |turn| turn.to_string());
+
+            if node.parents.is_empty() {
+                writeln!(csv, ",{},{},{}", node.id, node.birth_turn, death_turn).unwrap();
+            }
+
+            for parent in &node.parents {
+                writeln!(
+                    csv,
+                    "{},{},{},{}",
+                    parent, node.id, node.birth_turn, death_turn
+                )
+                .unwrap();
+            }
+        }
+
+        csv
+    }
+
+    /// Save as Newick when the extension is `nwk` or `newick`, as a CSV edge list otherwise
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let newick = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("nwk") | Some("newick")
+        );
+
+        if newick {
+            fs::write(path, self.to_newick())
+        } else {
+            fs::write(path, self.to_csv())
+        }
+    }
+}
+
+#[test]
+fn export_test() {
+    let mut phylogeny = Phylogeny::default();
+    let root = phylogeny.register(Vec::new(), 0);
+    let other = phylogeny.register(Vec::new(), 1);
+    let child = phylogeny.register(vec![root], 2);
+    let mated = phylogeny.register(vec![child, other], 5);
+    phylogeny.register(vec![root], 3);
+    phylogeny.record_death(child, 4);
+
+    assert_eq!(phylogeny.to_newick(), "((3:3)2:2,4:3)0;\n1;\n");
+    assert_eq!(
+        phylogeny.to_csv(),
+        "parent,child,birth_turn,death_turn\n,0,0,\n,1,1,\n0,2,2,4\n2,3,5,\n1,3,5,\n0,4,3,\n"
+    );
+
+    phylogeny.insert(Node {
+        id: 10,
+        parents: vec![mated],
+        birth_turn: 6,
+        death_turn: None,
+    });
+    assert!(phylogeny.contains(10));
+    assert_eq!(phylogeny.register(Vec::new(), 7), 11);
+}
+
+#[test]
+fn insertion_order_test() {
+    let node = |id, parents, birth_turn| Node {
+        id,
+        parents,
+        birth_turn,
+        death_turn: None,
+    };
+    let phylogeny = Phylogeny::from_nodes(vec![
+        node(5, vec![3], 4),
+        node(7, vec![5], 6),
+        node(3, Vec::new(), 1),
+    ])
+    .unwrap();
+
+    assert_eq!(phylogeny.to_newick(), "((7:2)5:3)3;\n");
+    assert_eq!(phylogeny.nodes().count(), 3);
+
+    assert!(Phylogeny::from_nodes(vec![node(3, Vec::new(), 1), node(3, Vec::new(), 2)]).is_err());
+}
+
+#[test]
+fn prune_test() {
+    let mut phylogeny = Phylogeny::default();
+    let root = phylogeny.register(Vec::new(), 0);
+    let other = phylogeny.register(Vec::new(), 0);
+    let child = phylogeny.register(vec![root], 1);
+    let mated = phylogeny.register(vec![child, other], 2);
+
+    // ancestors with a living descendant stay
+    for &id in [root, other, child].iter() {
+        phylogeny.record_death(id, 3);
+        phylogeny.prune(id);
+    }
+    assert_eq!(phylogeny.nodes().count(), 4);
+
+    phylogeny.record_death(mated, 4);
+    phylogeny.prune(mated);
+    assert_eq!(phylogeny.nodes().count(), 0);
+    assert_eq!(phylogeny.register(Vec::new(), 5), 4);
+}
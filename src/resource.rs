@@ -1,4 +1,17 @@
+use crate::component::DeathCause;
+use crate::dna::DNA;
+use crate::snapshot::SimulationSnapshot;
+use crate::stats::TurnStats;
+use crate::utils::ResumableRng;
+
 use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct GameSprites {
@@ -34,6 +47,34 @@ impl GameSprites {
     }
 }
 
+/// World random source, every other random stream is derived from this
+pub struct SimulationRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Derive an independent stream, e.g. for a new creature
+    pub fn fork(&mut self) -> ResumableRng {
+        ResumableRng::seed_from_u64(self.rng.gen())
+    }
+}
+
 #[derive(Clone)]
 pub enum SimulationState {
     Prepare {
@@ -74,4 +115,202 @@ impl SimulationState {
             turn_count: 0,
         }
     }
+
+    pub fn from_snapshot(snapshot: &SimulationSnapshot) -> Self {
+        let mut turn_timer = Timer::from_seconds(snapshot.turn_interval, true);
+        turn_timer.elapsed = snapshot.turn_elapsed;
+
+        SimulationState::Running {
+            daily_creature_count: snapshot.daily_creature_count,
+            daily_food_count: snapshot.daily_food_count,
+            turn_timer,
+            turn_count: snapshot.turn_count,
+        }
+    }
+
+    pub fn snapshot(&self) -> SimulationSnapshot {
+        match self {
+            SimulationState::Prepare {
+                daily_creature_count,
+                daily_food_count,
+                turn_interval,
+            } => SimulationSnapshot {
+                daily_creature_count: *daily_creature_count,
+                daily_food_count: *daily_food_count,
+                turn_interval: *turn_interval,
+                turn_elapsed: 0.0,
+                turn_count: 0,
+            },
+            SimulationState::Running {
+                daily_creature_count,
+                daily_food_count,
+                turn_timer,
+                turn_count,
+            } => SimulationSnapshot {
+                daily_creature_count: *daily_creature_count,
+                daily_food_count: *daily_food_count,
+                turn_interval: turn_timer.duration,
+                turn_elapsed: turn_timer.elapsed,
+                turn_count: *turn_count,
+            },
+        }
+    }
+
+    /// Advance the turn timer by one simulation step, true when a turn ended
+    pub fn step(&mut self, step: f32) -> bool {
+        if let SimulationState::Running {
+            turn_timer,
+            turn_count,
+            ..
+        } = self
+        {
+            turn_timer.tick(step);
+
+            if turn_timer.finished {
+                *turn_count += 1;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn turn_count(&self) -> usize {
+        match self {
+            SimulationState::Prepare { .. } => 0,
+            SimulationState::Running { turn_count, .. } => *turn_count,
+        }
+    }
+}
+
+/// Total count of dead creatures for each cause
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct DeathCount {
+    counts: BTreeMap<DeathCause, usize>,
+}
+
+impl DeathCount {
+    pub fn record(&mut self, cause: DeathCause) {
+        *self.counts.entry(cause).or_insert(0) += 1;
+    }
+
+    pub fn get(&self, cause: DeathCause) -> usize {
+        self.counts.get(&cause).copied().unwrap_or(0)
+    }
+
+    /// Add every count of `other`
+    pub fn add(&mut self, other: &DeathCount) {
+        for (&cause, &count) in &other.counts {
+            *self.counts.entry(cause).or_insert(0) += count;
+        }
+    }
+}
+
+/// Hand-written programs spawned as creatures at startup
+pub struct InitialPrograms(pub Vec<DNA>);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SnapshotAction {
+    Save,
+    Load,
+}
+
+pub struct SnapshotConfig {
+    pub path: PathBuf,
+    /// Save every given turns
+    pub autosave_interval: Option<usize>,
+    pub pending: Option<SnapshotAction>,
+    pub last_saved_turn: usize,
+}
+
+impl SnapshotConfig {
+    pub fn new(path: PathBuf, autosave_interval: Option<usize>, load: bool) -> Self {
+        Self {
+            path,
+            autosave_interval,
+            pending: if load {
+                Some(SnapshotAction::Load)
+            } else {
+                None
+            },
+            last_saved_turn: 0,
+        }
+    }
+}
+
+/// Turn count the app exits at
+pub struct TurnLimit {
+    pub turns: Option<usize>,
+    /// Totals of the run, set before exiting
+    pub summary: Arc<Mutex<Option<TurnStats>>>,
+}
+
+impl TurnLimit {
+    pub fn is_reached(&self, turn_count: usize) -> bool {
+        self.turns.map_or(false, |turns| turn_count >= turns)
+    }
+}
+
+/// Fixed logical step of simulation, independent of frame rate
+pub struct SimulationClock {
+    step: f32,
+    steps_per_frame: usize,
+}
+
+impl SimulationClock {
+    pub const MAX_STEPS_PER_FRAME: usize = 256;
+
+    pub fn new(step: f32, steps_per_frame: usize) -> Self {
+        let mut clock = Self {
+            step,
+            steps_per_frame: 1,
+        };
+        clock.set_steps_per_frame(steps_per_frame);
+        clock
+    }
+
+    /// Seconds advanced by one step
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    pub fn steps_per_frame(&self) -> usize {
+        self.steps_per_frame
+    }
+
+    pub fn set_steps_per_frame(&mut self, steps_per_frame: usize) {
+        self.steps_per_frame = steps_per_frame.clamp(1, Self::MAX_STEPS_PER_FRAME);
+    }
+}
+
+#[test]
+fn fixed_step_test() {
+    let clock = SimulationClock::new(0.125, 1000);
+    assert_eq!(
+        clock.steps_per_frame(),
+        SimulationClock::MAX_STEPS_PER_FRAME
+    );
+    assert_eq!(SimulationClock::new(0.125, 0).steps_per_frame(), 1);
+
+    // turns only depend on the step count, not on how steps are split into frames
+    let turns = |steps_per_frame: usize| {
+        let mut simulation = SimulationState::running(0, 0, 0.5);
+        let mut ended = Vec::new();
+
+        for frame in 0..60 / steps_per_frame {
+            for step in 0..steps_per_frame {
+                if simulation.step(clock.step()) {
+                    ended.push(frame * steps_per_frame + step);
+                }
+            }
+        }
+
+        assert_eq!(simulation.turn_count(), ended.len());
+        ended
+    };
+
+    assert_eq!(turns(1).len(), 15);
+    assert_eq!(turns(1), turns(4));
+    assert_eq!(turns(1), turns(60));
+    assert!(!SimulationState::prepare(0, 0, 0.5).step(1.0));
 }
@@ -0,0 +1,279 @@
+use crate::component::Creature;
+use crate::config::WorldConfig;
+use crate::constants::BaseType;
+use crate::utils::{convert_to_unit, convert_vec2_to_unit};
+
+use bevy::prelude::{Entity, Vec2};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+/// Cell size of the spatial grid used to find nearest objects
+const CELL_SIZE: f32 = 500.0;
+
+/// What a creature program can perceive, each writes `width` input cells
+///
+/// Values are zigzag encoded by `convert_to_unit`, except `Signal` which is passed as emitted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sensor {
+    /// Own translation, x and y
+    Position,
+    /// Offset to the nearest food, x and y, then its distance
+    NearestFood,
+    /// Offset to the nearest other creature, x and y, then its distance
+    NearestCreature,
+    /// Distance to the nearest wall
+    NearestWall,
+    Life,
+    /// Turns lived
+    Old,
+    /// Current velocity, x and y
+    Velocity,
+    /// Signal emitted by the nearest other creature
+    Signal,
+}
+
+impl Sensor {
+    pub const ALL: [Sensor; 8] = [
+        Sensor::Position,
+        Sensor::NearestFood,
+        Sensor::NearestCreature,
+        Sensor::NearestWall,
+        Sensor::Life,
+        Sensor::Old,
+        Sensor::Velocity,
+        Sensor::Signal,
+    ];
+
+    /// Number of input cells written by this sensor
+    pub fn width(self) -> usize {
+        match self {
+            Sensor::Position | Sensor::Velocity => 2,
+            Sensor::NearestFood | Sensor::NearestCreature => 3,
+            Sensor::NearestWall | Sensor::Life | Sensor::Old | Sensor::Signal => 1,
+        }
+    }
+}
+
+/// Spatial hash of positions for nearest neighbor lookups
+pub struct SpatialGrid<K> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<(K, Vec2)>>,
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl<K> SpatialGrid<K> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            min: (i32::MAX, i32::MAX),
+            max: (i32::MIN, i32::MIN),
+        }
+    }
+
+    fn cell(&self, pos: Vec2) -> (i32, i32) {
+        (
+            (pos.x() / self.cell_size).floor() as i32,
+            (pos.y() / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn insert(&mut self, key: K, pos: Vec2) {
+        let cell = self.cell(pos);
+
+        self.min = (self.min.0.min(cell.0), self.min.1.min(cell.1));
+        self.max = (self.max.0.max(cell.0), self.max.1.max(cell.1));
+        self.cells.entry(cell).or_default().push((key, pos));
+    }
+
+    /// Nearest object which is not `skip`ped
+    pub fn nearest(&self, pos: Vec2, skip: impl Fn(&K) -> bool) -> Option<&(K, Vec2)> {
+        self.nearest_within(pos, f32::INFINITY, skip)
+    }
+
+    /// Nearest object which is not `skip`ped, objects further than `radius` may not be found
+    pub fn nearest_within(
+        &self,
+        pos: Vec2,
+        radius: f32,
+        skip: impl Fn(&K) -> bool,
+    ) -> Option<&(K, Vec2)> {
+        if self.cells.is_empty() {
+            return None;
+        }
+
+        let (cx, cy) = self.cell(pos);
+        let max_ring = [
+            cx - self.min.0,
+            self.max.0 - cx,
+            cy - self.min.1,
+            self.max.1 - cy,
+        ]
+        .iter()
+        .map(|d| d.abs())
+        .max()
+        .unwrap_or(0)
+        .min(((radius / self.cell_size) as i32).saturating_add(1));
+
+        let mut best: Option<(f32, &(K, Vec2))> = None;
+
+        for ring in 0..=max_ring {
+            let mut visit = |cell: (i32, i32)| {
+                for object in self.cells.get(&cell).into_iter().flatten() {
+                    if skip(&object.0) {
+                        continue;
+                    }
+
+                    let distance = (object.1 - pos).length_squared();
+                    if !matches!(best, Some((best_distance, _)) if best_distance <= distance) {
+                        best = Some((distance, object));
+                    }
+                }
+            };
+
+            // cells on the border of the square of radius `ring`
+            for x in cx - ring..=cx + ring {
+                visit((x, cy - ring));
+                if ring != 0 {
+                    visit((x, cy + ring));
+                }
+            }
+            for y in cy - ring + 1..cy + ring {
+                visit((cx - ring, y));
+                visit((cx + ring, y));
+            }
+
+            // objects in further rings are at least this far
+            if let Some((distance, _)) = best {
+                if distance.sqrt() <= ring as f32 * self.cell_size {
+                    break;
+                }
+            }
+        }
+
+        best.map(|(_, object)| object)
+    }
+}
+
+/// Positions of every object creatures can perceive in the current step
+pub struct Surroundings {
+    foods: SpatialGrid<()>,
+    /// Keyed by entity and emitted signal
+    creatures: SpatialGrid<(Entity, BaseType)>,
+    world: WorldConfig,
+}
+
+impl Surroundings {
+    pub fn new(
+        world: WorldConfig,
+        foods: impl Iterator<Item = Vec2>,
+        creatures: impl Iterator<Item = (Entity, BaseType, Vec2)>,
+    ) -> Self {
+        let mut food_grid = SpatialGrid::new(CELL_SIZE);
+        let mut creature_grid = SpatialGrid::new(CELL_SIZE);
+
+        for translation in foods {
+            food_grid.insert((), translation);
+        }
+
+        for (entity, signal, translation) in creatures {
+            creature_grid.insert((entity, signal), translation);
+        }
+
+        Self {
+            foods: food_grid,
+            creatures: creature_grid,
+            world,
+        }
+    }
+
+    /// Program input of a creature, sensors are written in order
+    pub fn sense(
+        &self,
+        sensors: &[Sensor],
+        entity: Entity,
+        creature: &Creature,
+        translation: Vec2,
+    ) -> Vec<BaseType> {
+        let mut input = Vec::with_capacity(sensors.iter().map(|sensor| sensor.width()).sum());
+        let nearest_creature = || {
+            self.creatures
+                .nearest(translation, |(other, _signal)| *other == entity)
+        };
+
+        for sensor in sensors {
+            match sensor {
+                Sensor::Position => push_vec2(&mut input, translation),
+                Sensor::NearestFood => push_nearest(
+                    &mut input,
+                    translation,
+                    self.foods
+                        .nearest(translation, |_| false)
+                        .map(|(_, food)| *food),
+                ),
+                Sensor::NearestCreature => push_nearest(
+                    &mut input,
+                    translation,
+                    nearest_creature().map(|(_, creature)| *creature),
+                ),
+                Sensor::NearestWall => {
+                    input.push(convert_to_unit(self.world.wall_distance(translation)))
+                }
+                Sensor::Life => input.push(convert_to_unit(creature.life())),
+                Sensor::Old => input.push(convert_to_unit(creature.old() as f32)),
+                Sensor::Velocity => push_vec2(&mut input, creature.velocity()),
+                Sensor::Signal => {
+                    input.push(nearest_creature().map_or(0, |((_entity, signal), _)| *signal))
+                }
+            }
+        }
+
+        input
+    }
+}
+
+fn push_vec2(input: &mut Vec<BaseType>, vec2: Vec2) {
+    let (x, y) = convert_vec2_to_unit(vec2);
+    input.push(x);
+    input.push(y);
+}
+
+/// Offset and distance to `nearest`, all zero when there is none
+fn push_nearest(input: &mut Vec<BaseType>, translation: Vec2, nearest: Option<Vec2>) {
+    let offset = nearest.map_or(Vec2::zero(), |nearest| nearest - translation);
+    push_vec2(input, offset);
+    input.push(convert_to_unit(offset.length()));
+}
+
+#[test]
+fn nearest_test() {
+    let mut grid = SpatialGrid::new(10.0);
+    grid.insert(0, Vec2::new(0.0, 0.0));
+    grid.insert(1, Vec2::new(25.0, 0.0));
+    grid.insert(2, Vec2::new(-100.0, -100.0));
+
+    let nearest = |pos, skip: Option<i32>| grid.nearest(pos, |&key| Some(key) == skip).copied();
+
+    assert_eq!(
+        nearest(Vec2::new(1.0, 1.0), None),
+        Some((0, Vec2::new(0.0, 0.0)))
+    );
+    assert_eq!(
+        nearest(Vec2::new(1.0, 1.0), Some(0)),
+        Some((1, Vec2::new(25.0, 0.0)))
+    );
+    assert_eq!(
+        nearest(Vec2::new(-60.0, -60.0), None),
+        Some((2, Vec2::new(-100.0, -100.0)))
+    );
+    assert_eq!(
+        grid.nearest_within(Vec2::new(1.0, 1.0), 5.0, |&key| key == 0),
+        None
+    );
+    assert_eq!(
+        SpatialGrid::<()>::new(10.0).nearest(Vec2::zero(), |_| false),
+        None
+    );
+}
@@ -0,0 +1,293 @@
+use crate::dna::{Actions, DNA};
+use crate::hall_of_fame::HallOfFame;
+use crate::phylogeny::Node;
+use crate::resource::DeathCount;
+use crate::stats::TurnStats;
+use crate::utils::{seed_base, stream_seed, RngState};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Whole world state, saved and restored with [`Snapshot::save`] and [`Snapshot::load`]
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Seed of the run this snapshot was taken from
+    pub seed: u64,
+    pub simulation: SimulationSnapshot,
+    pub creatures: Vec<CreatureSnapshot>,
+    pub foods: Vec<FoodSnapshot>,
+    /// Lineage of every creature registered before the snapshot
+    #[serde(default)]
+    pub phylogeny: Vec<Node>,
+    #[serde(default)]
+    pub deaths: DeathCount,
+    #[serde(default)]
+    pub hall_of_fame: HallOfFame,
+    /// Run totals, without population statistics
+    #[serde(default)]
+    pub stats: TurnStats,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimulationSnapshot {
+    pub daily_creature_count: usize,
+    pub daily_food_count: usize,
+    pub turn_interval: f32,
+    pub turn_elapsed: f32,
+    pub turn_count: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreatureSnapshot {
+    pub translation: [f32; 3],
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub parents: Vec<u64>,
+    #[serde(default)]
+    pub birth_turn: usize,
+    pub life: f32,
+    pub old: usize,
+    pub generation: usize,
+    pub activated: bool,
+    pub velocity: [f32; 2],
+    pub tick_interval: f32,
+    pub tick_elapsed: f32,
+    /// Interpreter steps used in the current turn
+    #[serde(default)]
+    pub steps: usize,
+    /// Actions of the last program run
+    #[serde(default)]
+    pub actions: Actions,
+    /// Reproduce only by mating
+    #[serde(default)]
+    pub sexual: bool,
+    #[serde(default)]
+    pub food_eaten: usize,
+    #[serde(default)]
+    pub offspring: usize,
+    /// Mutations applied to the DNA inherited from parents
+    #[serde(default)]
+    pub mutations: usize,
+    /// Random stream, resumed at the same draw
+    pub rng: RngState,
+    pub dna: DNA,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FoodSnapshot {
+    pub translation: [f32; 3],
+}
+
+/// Saved genomes to seed creatures from
+#[derive(Serialize, Deserialize)]
+pub struct Library {
+    pub genomes: Vec<DNA>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Format(ron::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "io error: {}", err),
+            SnapshotError::Format(err) => write!(f, "format error: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SnapshotError {
+    fn from(err: ron::Error) -> Self {
+        SnapshotError::Format(err)
+    }
+}
+
+impl Snapshot {
+    /// Seed of every random source after loading, the same snapshot always continues the same way
+    pub fn resume_seed(&self) -> u64 {
+        stream_seed(seed_base(self.seed), self.simulation.turn_count as u64)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        let source = ron::ser::to_string_pretty(self, Default::default())?;
+        fs::write(path, source)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let file = BufReader::new(File::open(path)?);
+
+        Ok(ron::de::from_reader(file)?)
+    }
+}
+
+impl Library {
+    /// Load a library, or the genomes of every creature of a snapshot or a hall of fame
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let source = fs::read_to_string(path)?;
+
+        if let Ok(snapshot) = ron::de::from_str::<Snapshot>(&source) {
+            return Ok(Self {
+                genomes: snapshot
+                    .creatures
+                    .into_iter()
+                    .map(|creature| creature.dna)
+                    .collect(),
+            });
+        }
+
+        if let Ok(hall_of_fame) = ron::de::from_str::<HallOfFame>(&source) {
+            return Ok(Self {
+                genomes: hall_of_fame.genomes().cloned().collect(),
+            });
+        }
+
+        Ok(ron::de::from_str(&source)?)
+    }
+}
+
+#[test]
+fn round_trip_test() {
+    use crate::component::{Creature, DeathCause, Food};
+    use crate::config::{ActionConfig, MetabolismConfig, MutationConfig};
+    use crate::phylogeny::Phylogeny;
+    use crate::resource::SimulationState;
+    use crate::stats::StatsRecorder;
+    use crate::utils::test_rng;
+    use bevy::math::Vec3;
+
+    let metabolism = MetabolismConfig::default();
+    let action_config = ActionConfig::default();
+    let mut phylogeny = Phylogeny::default();
+    let mut simulation = SimulationState::running(10, 20, 0.5);
+    for _ in 0..3 {
+        simulation.step(0.25);
+    }
+
+    let mut parent = Creature::new("+>+".parse().unwrap(), test_rng(), &action_config);
+    parent.register(phylogeny.register(Vec::new(), 0), 0);
+    parent.try_eat_food(&mut Food::new(), &metabolism);
+    parent.has_moved(1.0, &metabolism);
+    let mut child = parent
+        .try_duplicate(&metabolism, &action_config, &MutationConfig::default())
+        .unwrap();
+    child.register(phylogeny.register(child.parents().to_vec(), 1), 1);
+
+    let mut deaths = DeathCount::default();
+    deaths.record(DeathCause::Starvation);
+    let mut hall_of_fame = HallOfFame::default();
+    hall_of_fame.record(&child.record(), 10);
+    let mut recorder = StatsRecorder::new(None);
+    recorder.current_mut().births += 2;
+    recorder.finish_turn(1, std::iter::empty()).unwrap();
+    recorder.current_mut().food_eaten += 1;
+
+    let mut creatures = [
+        (parent, Vec3::new(1.0, 2.0, 0.0)),
+        (child, Vec3::new(-3.0, 4.0, 0.0)),
+    ];
+    let snapshot = Snapshot {
+        seed: 42,
+        simulation: simulation.snapshot(),
+        creatures: creatures
+            .iter()
+            .map(|(creature, translation)| creature.snapshot(*translation))
+            .collect(),
+        foods: vec![Food::new().snapshot(Vec3::new(5.0, 6.0, 0.0))],
+        phylogeny: phylogeny.nodes().cloned().collect(),
+        deaths,
+        hall_of_fame,
+        stats: recorder.counts(),
+    };
+
+    let path = std::env::temp_dir().join(format!("snapshot-test-{}.ron", std::process::id()));
+    snapshot.save(&path).unwrap();
+    let loaded = Snapshot::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let source = |snapshot: &Snapshot| ron::ser::to_string(snapshot).unwrap();
+    assert_eq!(source(&loaded), source(&snapshot));
+    assert_eq!(loaded.resume_seed(), snapshot.resume_seed());
+
+    let restored = SimulationState::from_snapshot(&loaded.simulation);
+    assert_eq!(restored.turn_count(), 1);
+    assert_eq!(
+        ron::ser::to_string(&restored.snapshot()).unwrap(),
+        ron::ser::to_string(&snapshot.simulation).unwrap()
+    );
+
+    let mut recorder = StatsRecorder::new(None);
+    recorder.restore(loaded.stats, 1);
+    assert_eq!(recorder.counts().births, 2);
+    assert_eq!(recorder.counts().food_eaten, 1);
+    assert_eq!(loaded.deaths.get(DeathCause::Starvation), 1);
+
+    // Eat, duplicate and age, the child DNA is mutated with the creature random stream
+    let step = |creature: &mut Creature, translation: Vec3| {
+        creature.try_eat_food(&mut Food::new(), &metabolism);
+        let child = creature
+            .try_duplicate(&metabolism, &action_config, &MutationConfig::default())
+            .map(|child| ron::ser::to_string(&child.snapshot(translation)).unwrap());
+        creature.time_pass(&metabolism);
+        (
+            ron::ser::to_string(&creature.snapshot(translation)).unwrap(),
+            child,
+        )
+    };
+
+    for (creature, (original, translation)) in loaded.creatures.into_iter().zip(&mut creatures) {
+        let mut restored = Creature::from_snapshot(creature);
+        assert_eq!(
+            ron::ser::to_string(&restored.snapshot(*translation)).unwrap(),
+            ron::ser::to_string(&original.snapshot(*translation)).unwrap()
+        );
+
+        let expected = step(original, *translation);
+        assert!(expected.1.is_some());
+        assert_eq!(step(&mut restored, *translation), expected);
+    }
+
+    let restored = Phylogeny::from_nodes(loaded.phylogeny).unwrap();
+    assert_eq!(restored.to_csv(), phylogeny.to_csv());
+    assert_eq!(restored.to_newick(), "(1:1)0;\n");
+}
+
+#[test]
+fn resume_seed_test() {
+    let snapshot = |seed, turn_count| Snapshot {
+        seed,
+        simulation: SimulationSnapshot {
+            daily_creature_count: 0,
+            daily_food_count: 0,
+            turn_interval: 0.5,
+            turn_elapsed: 0.0,
+            turn_count,
+        },
+        creatures: Vec::new(),
+        foods: Vec::new(),
+        phylogeny: Vec::new(),
+        deaths: DeathCount::default(),
+        hall_of_fame: HallOfFame::default(),
+        stats: TurnStats::default(),
+    };
+
+    // `seed ^ turn_count` would give the same seed to both
+    assert!(snapshot(0, 1).resume_seed() != snapshot(1, 0).resume_seed());
+    assert!(snapshot(5, 3).resume_seed() != snapshot(5, 4).resume_seed());
+    assert_eq!(snapshot(5, 3).resume_seed(), snapshot(5, 3).resume_seed());
+}
@@ -0,0 +1,319 @@
+use crate::component::{Creature, DeathCause};
+use crate::resource::DeathCount;
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+/// Time series row of one turn
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TurnStats {
+    /// Turn count once the turn finished
+    pub turn: usize,
+    pub population: usize,
+    pub births: usize,
+    /// Births without parent
+    pub seeded: usize,
+    pub deaths: DeathCount,
+    pub food_eaten: usize,
+    /// Mutations applied to the DNA of born creatures
+    pub mutations: usize,
+    pub mean_generation: f32,
+    pub max_generation: usize,
+    pub mean_life: f32,
+    /// Min, quartiles and max of genome sizes
+    pub genome_size: [usize; 5],
+}
+
+impl TurnStats {
+    /// Set population statistics from living `creatures`
+    fn measure<'a>(&mut self, creatures: impl Iterator<Item = &'a Creature>) {
+        let mut generation_sum = 0;
+        let mut life_sum = 0.0;
+        let mut sizes = Vec::new();
+
+        for creature in creatures {
+            generation_sum += creature.generation();
+            self.max_generation = self.max_generation.max(creature.generation());
+            life_sum += creature.life();
+            sizes.push(creature.dna().size());
+        }
+
+        self.population = sizes.len();
+
+        if !sizes.is_empty() {
+            self.mean_generation = generation_sum as f32 / sizes.len() as f32;
+            self.mean_life = life_sum / sizes.len() as f32;
+
+            sizes.sort_unstable();
+            for (quartile, size) in self.genome_size.iter_mut().enumerate() {
+                *size = sizes[(sizes.len() - 1) * quartile / 4];
+            }
+        }
+    }
+
+    /// Add the counters of `other`, leaving population statistics
+    fn add_counts(&mut self, other: &TurnStats) {
+        self.births += other.births;
+        self.seeded += other.seeded;
+        self.deaths.add(&other.deaths);
+        self.food_eaten += other.food_eaten;
+        self.mutations += other.mutations;
+    }
+
+    /// Name and value of each column, in order
+    pub fn columns(&self) -> Vec<(String, String)> {
+        let mut columns = vec![
+            (String::from("turn"), self.turn.to_string()),
+            (String::from("population"), self.population.to_string()),
+            (String::from("births"), self.births.to_string()),
+            (String::from("seeded"), self.seeded.to_string()),
+        ];
+
+        for &cause in DeathCause::ALL.iter() {
+            columns.push((
+                format!("deaths_{}", snake_case(&format!("{:?}", cause))),
+                self.deaths.get(cause).to_string(),
+            ));
+        }
+
+        columns.extend(vec![
+            (String::from("food_eaten"), self.food_eaten.to_string()),
+            (String::from("mutations"), self.mutations.to_string()),
+            (
+                String::from("mean_generation"),
+                self.mean_generation.to_string(),
+            ),
+            (
+                String::from("max_generation"),
+                self.max_generation.to_string(),
+            ),
+            (String::from("mean_life"), self.mean_life.to_string()),
+        ]);
+
+        for (name, size) in ["min", "q1", "median", "q3", "max"]
+            .iter()
+            .zip(&self.genome_size)
+        {
+            columns.push((format!("genome_size_{}", name), size.to_string()));
+        }
+
+        columns
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for (idx, c) in name.chars().enumerate() {
+        if c.is_uppercase() && idx != 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Csv,
+    JsonLines,
+}
+
+impl Format {
+    fn row(self, stats: &TurnStats, header: bool) -> String {
+        let columns = stats.columns();
+        let mut row = String::new();
+
+        match self {
+            Format::Csv => {
+                if header {
+                    let names: Vec<_> = columns.iter().map(|(name, _)| name.as_str()).collect();
+                    writeln!(row, "{}", names.join(",")).unwrap();
+                }
+
+                let values: Vec<_> = columns.iter().map(|(_, value)| value.as_str()).collect();
+                writeln!(row, "{}", values.join(",")).unwrap();
+            }
+            Format::JsonLines => {
+                let fields: Vec<_> = columns
+                    .iter()
+                    .map(|(name, value)| format!("\"{}\":{}", name, value))
+                    .collect();
+                writeln!(row, "{{{}}}", fields.join(",")).unwrap();
+            }
+        }
+
+        row
+    }
+}
+
+/// Stream a `TurnStats` row per turn to a file and sum them up for the run
+///
+/// Written as JSON Lines with a `jsonl` or `json` extension and as CSV otherwise
+pub struct StatsRecorder {
+    path: Option<PathBuf>,
+    writer: Option<BufWriter<File>>,
+    /// Counters of the turn in progress
+    current: TurnStats,
+    /// Counters of every finished turn
+    totals: TurnStats,
+    last_turn: usize,
+}
+
+impl StatsRecorder {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            writer: None,
+            current: TurnStats::default(),
+            totals: TurnStats::default(),
+            last_turn: 0,
+        }
+    }
+
+    pub fn current_mut(&mut self) -> &mut TurnStats {
+        &mut self.current
+    }
+
+    /// Turn count when the last turn finished
+    pub fn last_turn(&self) -> usize {
+        self.last_turn
+    }
+
+    fn format(&self) -> Format {
+        match self
+            .path
+            .as_ref()
+            .and_then(|path| path.extension())
+            .and_then(|ext| ext.to_str())
+        {
+            Some("jsonl") | Some("json") => Format::JsonLines,
+            _ => Format::Csv,
+        }
+    }
+
+    /// Write the turn in progress measured with the living `creatures` and start a new one
+    ///
+    /// Recording stops on error
+    pub fn finish_turn<'a>(
+        &mut self,
+        turn: usize,
+        creatures: impl Iterator<Item = &'a Creature>,
+    ) -> io::Result<()> {
+        let mut stats = std::mem::take(&mut self.current);
+        stats.turn = turn;
+        self.last_turn = turn;
+        self.totals.add_counts(&stats);
+
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        stats.measure(creatures);
+
+        let header = self.writer.is_none();
+        if header {
+            match File::create(path) {
+                Ok(file) => self.writer = Some(BufWriter::new(file)),
+                Err(err) => {
+                    self.path = None;
+                    return Err(err);
+                }
+            }
+        }
+
+        let row = self.format().row(&stats, header);
+        let writer = self.writer.as_mut().unwrap();
+        let result = writer
+            .write_all(row.as_bytes())
+            .and_then(|()| writer.flush());
+
+        if result.is_err() {
+            self.path = None;
+            self.writer = None;
+        }
+
+        result
+    }
+
+    /// Counters of every turn, including the one in progress
+    pub fn counts(&self) -> TurnStats {
+        let mut stats = TurnStats::default();
+        stats.add_counts(&self.totals);
+        stats.add_counts(&self.current);
+        stats
+    }
+
+    /// Continue from the `counts` of a run loaded at `turn`
+    pub fn restore(&mut self, counts: TurnStats, turn: usize) {
+        self.totals = counts;
+        self.current = TurnStats::default();
+        self.last_turn = turn;
+    }
+
+    /// Totals up to `turn`, including the turn in progress, measured with the living `creatures`
+    pub fn summary<'a>(
+        &self,
+        turn: usize,
+        creatures: impl Iterator<Item = &'a Creature>,
+    ) -> TurnStats {
+        let mut stats = self.counts();
+        stats.turn = turn;
+        stats.measure(creatures);
+        stats
+    }
+}
+
+#[test]
+fn row_test() {
+    let mut stats = TurnStats {
+        turn: 3,
+        population: 10,
+        genome_size: [1, 2, 3, 4, 5],
+        ..TurnStats::default()
+    };
+    stats.deaths.record(DeathCause::OutOfBox);
+
+    let csv = Format::Csv.row(&stats, true);
+    let mut lines = csv.lines();
+    let names: Vec<_> = lines.next().unwrap().split(',').collect();
+    let values: Vec<_> = lines.next().unwrap().split(',').collect();
+
+    assert_eq!(names.len(), values.len());
+    assert_eq!(names[..2], ["turn", "population"]);
+    assert_eq!(values[..2], ["3", "10"]);
+    assert!(names.contains(&"deaths_out_of_box"));
+    assert_eq!(names.last(), Some(&"genome_size_max"));
+    assert_eq!(values.last(), Some(&"5"));
+    assert_eq!(Format::Csv.row(&stats, false).lines().count(), 1);
+
+    let json = Format::JsonLines.row(&stats, false);
+    assert!(json.starts_with("{\"turn\":3,\"population\":10,"));
+    assert!(json.contains("\"deaths_out_of_box\":1"));
+    assert!(json.ends_with("\"genome_size_max\":5}\n"));
+}
+
+#[test]
+fn summary_test() {
+    let mut recorder = StatsRecorder::new(None);
+
+    for turn in 1..=3 {
+        let stats = recorder.current_mut();
+        stats.births += 2;
+        stats.deaths.record(DeathCause::OutOfBox);
+        recorder.finish_turn(turn, std::iter::empty()).unwrap();
+    }
+    recorder.current_mut().births += 1;
+
+    let summary = recorder.summary(4, std::iter::empty());
+    assert_eq!(summary.turn, 4);
+    assert_eq!(summary.births, 7);
+    assert_eq!(summary.deaths.get(DeathCause::OutOfBox), 3);
+    assert_eq!(summary.population, 0);
+}
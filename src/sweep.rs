@@ -0,0 +1,213 @@
+use crate::experiment::{ConfigError, ExperimentConfig};
+use crate::stats::TurnStats;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Values taken by one field of `ExperimentConfig`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Parameter {
+    /// Field path given to `ExperimentConfig::set`, like `mutation.rate`
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/// Headless runs of every combination of parameter values, loaded from a RON file
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SweepConfig {
+    /// Experiment every combination starts from
+    pub base: ExperimentConfig,
+    pub parameters: Vec<Parameter>,
+    /// Runs of each combination, seeded from `seed` upward
+    ///
+    /// Every combination gets the same seeds
+    pub seeds: usize,
+    pub seed: u64,
+    /// Turns simulated by each run
+    pub turns: usize,
+    /// Runs at once, one per core when 0
+    pub threads: usize,
+    /// Summary CSV with a row per run
+    pub output: PathBuf,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            base: ExperimentConfig::default(),
+            parameters: Vec::new(),
+            seeds: 1,
+            seed: 0,
+            turns: 100,
+            threads: 0,
+            output: PathBuf::from("sweep.csv"),
+        }
+    }
+}
+
+/// One combination run with one seed
+pub struct Run {
+    pub index: usize,
+    pub seed: u64,
+    /// Value of each parameter, in order
+    pub values: Vec<f64>,
+    pub experiment: ExperimentConfig,
+}
+
+impl SweepConfig {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = ron::de::from_str(&fs::read_to_string(path)?)?;
+        config.runs()?;
+
+        Ok(config)
+    }
+
+    /// Every run, the last parameter changing fastest
+    pub fn runs(&self) -> Result<Vec<Run>, ConfigError> {
+        if self.seeds == 0 {
+            return Err(ConfigError::Invalid("seeds", "must be positive"));
+        }
+        if self.turns == 0 {
+            return Err(ConfigError::Invalid("turns", "must be positive"));
+        }
+        if self.parameters.iter().any(|param| param.values.is_empty()) {
+            return Err(ConfigError::Invalid(
+                "parameters",
+                "values must not be empty",
+            ));
+        }
+
+        let combinations = self
+            .parameters
+            .iter()
+            .map(|param| param.values.len())
+            .product::<usize>();
+        let mut runs = Vec::with_capacity(combinations * self.seeds);
+
+        for combination in 0..combinations {
+            let mut experiment = self.base.clone();
+            let mut values = vec![0.0; self.parameters.len()];
+            let mut rest = combination;
+
+            for (idx, param) in self.parameters.iter().enumerate().rev() {
+                values[idx] = param.values[rest % param.values.len()];
+                rest /= param.values.len();
+                experiment.set(&param.name, values[idx])?;
+            }
+
+            experiment.validate()?;
+
+            for offset in 0..self.seeds {
+                runs.push(Run {
+                    index: runs.len(),
+                    seed: self.seed.wrapping_add(offset as u64),
+                    values: values.clone(),
+                    experiment: experiment.clone(),
+                });
+            }
+        }
+
+        Ok(runs)
+    }
+
+    /// Column names of the summary
+    pub fn header(&self) -> String {
+        let mut names = vec![String::from("run"), String::from("seed")];
+        names.extend(self.parameters.iter().map(|param| param.name.clone()));
+        names.extend(
+            TurnStats::default()
+                .columns()
+                .into_iter()
+                .map(|(name, _)| name),
+        );
+
+        names.join(",") + "\n"
+    }
+
+    /// Summary row of `run` ended with `stats`
+    pub fn row(&self, run: &Run, stats: &TurnStats) -> String {
+        let mut values = vec![run.index.to_string(), run.seed.to_string()];
+        values.extend(run.values.iter().map(|value| value.to_string()));
+        values.extend(stats.columns().into_iter().map(|(_, value)| value));
+
+        values.join(",") + "\n"
+    }
+}
+
+#[test]
+fn runs_test() {
+    let mut sweep: SweepConfig = ron::de::from_str(
+        "(
+            parameters: [
+                (name: \"simulation.daily_food_count\", values: [100, 200, 300]),
+                (name: \"mutation.rate\", values: [0.5, 1.0]),
+            ],
+            seeds: 2,
+            seed: 7,
+        )",
+    )
+    .unwrap();
+
+    let runs = sweep.runs().unwrap();
+    assert_eq!(runs.len(), 12);
+    assert_eq!(runs[0].values, [100.0, 0.5]);
+    assert_eq!(runs[2].values, [100.0, 1.0]);
+    assert_eq!(runs[11].values, [300.0, 1.0]);
+    assert_eq!(runs[11].index, 11);
+    assert_eq!((runs[4].seed, runs[5].seed), (7, 8));
+    assert_eq!(runs[4].experiment.simulation.daily_food_count, 200);
+    assert_eq!(runs[4].experiment.mutation.rate, 0.5);
+
+    sweep.parameters[1].values.push(-1.0);
+    match sweep.runs() {
+        Err(ConfigError::Invalid(field, _)) => assert_eq!(field, "mutation.rate"),
+        _ => panic!("negative rate should be invalid"),
+    }
+
+    sweep.parameters[1].name = String::from("mutation.rat");
+    assert!(matches!(
+        sweep.runs(),
+        Err(ConfigError::UnknownParameter(_))
+    ));
+
+    assert_eq!(SweepConfig::default().runs().unwrap().len(), 1);
+}
+
+#[test]
+fn summary_row_test() {
+    let sweep = SweepConfig {
+        parameters: vec![Parameter {
+            name: String::from("metabolism.food_energy"),
+            values: vec![1.5],
+        }],
+        ..SweepConfig::default()
+    };
+    let runs = sweep.runs().unwrap();
+    let stats = TurnStats {
+        turn: 100,
+        population: 42,
+        ..TurnStats::default()
+    };
+
+    let header = sweep.header();
+    let row = sweep.row(&runs[0], &stats);
+    let names: Vec<_> = header.trim_end().split(',').collect();
+    let values: Vec<_> = row.trim_end().split(',').collect();
+
+    assert_eq!(names.len(), values.len());
+    assert_eq!(
+        names[..5],
+        [
+            "run",
+            "seed",
+            "metabolism.food_energy",
+            "turn",
+            "population"
+        ]
+    );
+    assert_eq!(values[..5], ["0", "0", "1.5", "100", "42"]);
+}
@@ -1,26 +1,86 @@
 mod collision;
+mod death;
+mod hall_of_fame;
 mod life_display;
 mod movement;
+mod phylogeny;
 mod setup;
 mod simulation;
+mod snapshot;
+mod sprite;
+mod stats;
+mod step;
 mod tick;
 mod turn;
 mod ui_update;
 
-use crate::constants::BACK_COLOR;
-use crate::resource::SimulationState;
+use crate::config::{
+    ActionConfig, GenomeConfig, HallOfFameConfig, InterpreterConfig, MatingConfig,
+    MetabolismConfig, MutationConfig, PhylogenyConfig, PoolConfig, SeedingConfig, SensorConfig,
+    StatsConfig, WorldConfig,
+};
+use crate::constants::{BACK_COLOR, SIMULATION_STEP};
+use crate::dna::{DnaPool, Seeder, DNA};
+use crate::event::{CreatureBorn, CreatureDied, FoodEaten};
+use crate::experiment::ExperimentConfig;
+use crate::hall_of_fame::HallOfFame;
+use crate::phylogeny::Phylogeny;
+use crate::resource::{
+    DeathCount, InitialPrograms, SimulationClock, SimulationRng, SimulationState, SnapshotConfig,
+    TurnLimit,
+};
+use crate::stats::{StatsRecorder, TurnStats};
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
-use bevy::prelude::{ClearColor, IntoQuerySystem, Plugin};
+use bevy::ecs::Schedule;
+use bevy::prelude::{ClearColor, Events, IntoQuerySystem, IntoThreadLocalSystem, Plugin};
 
 use self::{
-    collision::collision_system, life_display::life_display_system, movement::movement_system,
-    setup::setup, simulation::prepare_simulation_system, tick::tick_system, turn::turn_system,
+    collision::collision_system,
+    death::death_count_system,
+    hall_of_fame::{hall_of_fame_exit_system, hall_of_fame_system},
+    life_display::life_display_system,
+    movement::movement_system,
+    phylogeny::{phylogeny_exit_system, phylogeny_system},
+    setup::setup,
+    simulation::{prepare_simulation_system, spawn_program_system},
+    snapshot::{snapshot_input_system, snapshot_system},
+    sprite::sprite_system,
+    stats::{stats_system, turn_limit_system},
+    step::{simulation_step_system, speed_control_system, SIMULATION_STAGE, TURN_STAGE},
+    tick::tick_system,
+    turn::turn_system,
     ui_update::ui_update_system,
 };
-use bevy::app::AppBuilder;
+use bevy::app::{stage, AppBuilder};
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 pub struct NaturalSelectionPlugin {
     init_simulation_state: SimulationState,
+    headless: bool,
+    seed: u64,
+    steps_per_frame: usize,
+    snapshot_path: PathBuf,
+    autosave_interval: Option<usize>,
+    load_snapshot: bool,
+    programs: Vec<DNA>,
+    world_config: WorldConfig,
+    interpreter_config: InterpreterConfig,
+    metabolism_config: MetabolismConfig,
+    sensor_config: SensorConfig,
+    action_config: ActionConfig,
+    mating_config: MatingConfig,
+    mutation_config: MutationConfig,
+    genome_config: GenomeConfig,
+    pool_config: PoolConfig,
+    seeding_config: SeedingConfig,
+    library: Vec<DNA>,
+    hall_of_fame_config: HallOfFameConfig,
+    phylogeny_config: PhylogenyConfig,
+    stats_config: StatsConfig,
+    turn_limit: Option<usize>,
+    summary: Arc<Mutex<Option<TurnStats>>>,
 }
 
 impl NaturalSelectionPlugin {
@@ -31,22 +91,250 @@ impl NaturalSelectionPlugin {
                 daily_food_count,
                 turn_interval,
             ),
+            headless: false,
+            seed: 0,
+            steps_per_frame: 1,
+            snapshot_path: PathBuf::from("snapshot.ron"),
+            autosave_interval: None,
+            load_snapshot: false,
+            programs: Vec::new(),
+            world_config: WorldConfig::default(),
+            interpreter_config: InterpreterConfig::default(),
+            metabolism_config: MetabolismConfig::default(),
+            sensor_config: SensorConfig::default(),
+            action_config: ActionConfig::default(),
+            mating_config: MatingConfig::default(),
+            mutation_config: MutationConfig::default(),
+            genome_config: GenomeConfig::default(),
+            pool_config: PoolConfig::default(),
+            seeding_config: SeedingConfig::default(),
+            library: Vec::new(),
+            hall_of_fame_config: HallOfFameConfig::default(),
+            phylogeny_config: PhylogenyConfig::default(),
+            stats_config: StatsConfig::default(),
+            turn_limit: None,
+            summary: Arc::new(Mutex::new(None)),
         }
     }
+
+    /// Run only the simulation systems, without any sprite, ui or renderer
+    pub fn headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Seed for every random source in the simulation
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Simulation steps run per frame, for fast-forward
+    pub fn steps_per_frame(mut self, steps_per_frame: usize) -> Self {
+        self.steps_per_frame = steps_per_frame;
+        self
+    }
+
+    /// Snapshot file, saved every `autosave_interval` turns and restored at startup if `load`
+    pub fn snapshot(mut self, path: PathBuf, autosave_interval: Option<usize>, load: bool) -> Self {
+        self.snapshot_path = path;
+        self.autosave_interval = autosave_interval;
+        self.load_snapshot = load;
+        self
+    }
+
+    /// Spawn a creature for each program at startup
+    pub fn programs(mut self, programs: Vec<DNA>) -> Self {
+        self.programs = programs;
+        self
+    }
+
+    pub fn interpreter_config(mut self, config: InterpreterConfig) -> Self {
+        self.interpreter_config = config;
+        self
+    }
+
+    pub fn metabolism_config(mut self, config: MetabolismConfig) -> Self {
+        self.metabolism_config = config;
+        self
+    }
+
+    pub fn sensor_config(mut self, config: SensorConfig) -> Self {
+        self.sensor_config = config;
+        self
+    }
+
+    pub fn action_config(mut self, config: ActionConfig) -> Self {
+        self.action_config = config;
+        self
+    }
+
+    pub fn mating_config(mut self, config: MatingConfig) -> Self {
+        self.mating_config = config;
+        self
+    }
+
+    pub fn mutation_config(mut self, config: MutationConfig) -> Self {
+        self.mutation_config = config;
+        self
+    }
+
+    pub fn genome_config(mut self, config: GenomeConfig) -> Self {
+        self.genome_config = config;
+        self
+    }
+
+    pub fn world_config(mut self, config: WorldConfig) -> Self {
+        self.world_config = config;
+        self
+    }
+
+    /// Every config of `experiment` except the simulation one, given to `new`
+    pub fn experiment(self, experiment: ExperimentConfig) -> Self {
+        self.world_config(experiment.world)
+            .interpreter_config(experiment.interpreter)
+            .metabolism_config(experiment.metabolism)
+            .sensor_config(experiment.sensor)
+            .action_config(experiment.action)
+            .mating_config(experiment.mating)
+            .mutation_config(experiment.mutation)
+            .genome_config(experiment.genome)
+            .pool_config(experiment.pool)
+            .seeding_config(experiment.seeding)
+            .hall_of_fame_config(experiment.hall_of_fame)
+            .phylogeny_config(experiment.phylogeny)
+            .stats_config(experiment.stats)
+    }
+
+    pub fn pool_config(mut self, config: PoolConfig) -> Self {
+        self.pool_config = config;
+        self
+    }
+
+    pub fn seeding_config(mut self, config: SeedingConfig) -> Self {
+        self.seeding_config = config;
+        self
+    }
+
+    /// Genomes sampled by the library ratio of `SeedingConfig`
+    pub fn library(mut self, library: Vec<DNA>) -> Self {
+        self.library = library;
+        self
+    }
+
+    pub fn hall_of_fame_config(mut self, config: HallOfFameConfig) -> Self {
+        self.hall_of_fame_config = config;
+        self
+    }
+
+    pub fn phylogeny_config(mut self, config: PhylogenyConfig) -> Self {
+        self.phylogeny_config = config;
+        self
+    }
+
+    pub fn stats_config(mut self, config: StatsConfig) -> Self {
+        self.stats_config = config;
+        self
+    }
+
+    /// Exit after `turns` turns, storing the run totals in `summary`
+    pub fn turn_limit(mut self, turns: usize, summary: Arc<Mutex<Option<TurnStats>>>) -> Self {
+        self.turn_limit = Some(turns);
+        self.summary = summary;
+        self
+    }
+
+    fn simulation_schedule(&self) -> Schedule {
+        let mut schedule = Schedule::default();
+
+        schedule.add_stage(SIMULATION_STAGE);
+        schedule.add_stage(TURN_STAGE);
+        schedule
+            .add_system_to_stage(
+                SIMULATION_STAGE,
+                Events::<CreatureDied>::update_system.system(),
+            )
+            .add_system_to_stage(
+                SIMULATION_STAGE,
+                Events::<CreatureBorn>::update_system.system(),
+            )
+            .add_system_to_stage(
+                SIMULATION_STAGE,
+                Events::<FoodEaten>::update_system.system(),
+            )
+            .add_system_to_stage(SIMULATION_STAGE, phylogeny_system.system())
+            .add_system_to_stage(SIMULATION_STAGE, stats_system.system())
+            .add_system_to_stage(SIMULATION_STAGE, collision_system.system())
+            .add_system_to_stage(SIMULATION_STAGE, movement_system.system())
+            .add_system_to_stage(SIMULATION_STAGE, tick_system.system())
+            .add_system_to_stage(TURN_STAGE, turn_system.system())
+            .add_system_to_stage(TURN_STAGE, death_count_system.system())
+            .add_system_to_stage(TURN_STAGE, hall_of_fame_system.system());
+
+        schedule
+    }
 }
 
 impl Plugin for NaturalSelectionPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_plugin(FrameTimeDiagnosticsPlugin::default())
-            .add_resource(ClearColor(BACK_COLOR))
-            .add_resource(self.init_simulation_state.clone())
-            .add_startup_system(setup.system())
+        app.add_resource(self.init_simulation_state.clone())
+            .add_resource(SimulationRng::new(self.seed))
+            .add_resource(Seeder::new(
+                self.seeding_config,
+                self.library.clone(),
+                DnaPool::new(
+                    self.seed,
+                    self.genome_config.kinds.clone(),
+                    self.pool_config,
+                    self.interpreter_config,
+                    self.sensor_config.width(),
+                ),
+            ))
+            .add_resource(SimulationClock::new(SIMULATION_STEP, self.steps_per_frame))
+            .add_resource(SnapshotConfig::new(
+                self.snapshot_path.clone(),
+                self.autosave_interval,
+                self.load_snapshot,
+            ))
+            .add_resource(self.world_config)
+            .add_resource(self.interpreter_config)
+            .add_resource(self.metabolism_config)
+            .add_resource(self.sensor_config.clone())
+            .add_resource(self.action_config)
+            .add_resource(self.mating_config)
+            .add_resource(self.mutation_config)
+            .add_resource(Events::<CreatureDied>::default())
+            .add_resource(Events::<CreatureBorn>::default())
+            .add_resource(Events::<FoodEaten>::default())
+            .add_resource(DeathCount::default())
+            .add_resource(self.hall_of_fame_config.clone())
+            .add_resource(HallOfFame::default())
+            .add_resource(self.phylogeny_config.clone())
+            .add_resource(Phylogeny::default())
+            .add_resource(StatsRecorder::new(self.stats_config.path.clone()))
+            .add_resource(TurnLimit {
+                turns: self.turn_limit,
+                summary: self.summary.clone(),
+            })
+            .add_resource(InitialPrograms(self.programs.clone()))
+            .add_startup_system(spawn_program_system.system())
             .add_system(prepare_simulation_system.system())
-            .add_system(collision_system.system())
-            .add_system(movement_system.system())
-            .add_system(tick_system.system())
-            .add_system(turn_system.system())
-            .add_system(life_display_system.system())
-            .add_system(ui_update_system.system());
+            .add_system(simulation_step_system(self.simulation_schedule()).thread_local_system())
+            .add_system(snapshot_system.system())
+            .add_system(turn_limit_system.system())
+            // AppExit is sent in the update stage and the app stops after this frame
+            .add_system_to_stage(stage::LAST, phylogeny_exit_system.system())
+            .add_system_to_stage(stage::LAST, hall_of_fame_exit_system.system());
+
+        if !self.headless {
+            app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+                .add_resource(ClearColor(BACK_COLOR))
+                .add_startup_system(setup.system())
+                .add_system(speed_control_system.system())
+                .add_system(snapshot_input_system.system())
+                .add_system(sprite_system.system())
+                .add_system(life_display_system.system())
+                .add_system(ui_update_system.system());
+        }
     }
 }
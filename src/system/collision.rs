@@ -1,30 +1,156 @@
-use crate::component::{Creature, Food};
+use crate::component::{Creature, Food, Newborn};
+use crate::config::{ActionConfig, MatingConfig, MetabolismConfig, MutationConfig, WorldConfig};
+use crate::event::FoodEaten;
+use crate::resource::SimulationClock;
+use crate::sensor::SpatialGrid;
 
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide;
 
+use std::collections::HashSet;
+
 pub fn collision_system(
     mut commands: Commands,
-    mut creature_query: Query<(&mut Creature, &Transform, &Sprite)>,
-    mut food_query: Query<(Entity, &mut Food, &Transform, &Sprite)>,
+    clock: Res<SimulationClock>,
+    config: Res<ActionConfig>,
+    mating: Res<MatingConfig>,
+    metabolism: Res<MetabolismConfig>,
+    mutation: Res<MutationConfig>,
+    world: Res<WorldConfig>,
+    mut eaten_events: ResMut<Events<FoodEaten>>,
+    mut creature_query: Query<(Entity, &mut Creature, &Transform)>,
+    mut food_query: Query<(Entity, &mut Food, &Transform)>,
 ) {
-    for (mut creature, creature_transform, sprite) in &mut creature_query.iter() {
-        let creature_size = sprite.size;
+    let creature_size = world.creature_size();
+    let food_size = world.food_size();
+
+    for (_creature_entity, mut creature, creature_transform) in &mut creature_query.iter() {
+        if !creature.can_eat(&config) {
+            continue;
+        }
 
-        for (food_entity, mut food, food_transform, sprite) in &mut food_query.iter() {
+        for (food_entity, mut food, food_transform) in &mut food_query.iter() {
             let collision = collide(
                 creature_transform.translation,
                 creature_size,
                 food_transform.translation,
-                sprite.size,
+                food_size,
             );
 
             if let Some(_collision) = collision {
                 // eat
-                if creature.try_eat_food(&mut food) {
+                if creature.try_eat_food(&mut food, &metabolism) {
                     commands.despawn(food_entity);
+                    eaten_events.send(FoodEaten);
                 }
             }
         }
     }
+
+    // attack the nearest touched creature
+    let mut creatures = SpatialGrid::new(creature_size.x());
+    for (creature_entity, _creature, transform) in &mut creature_query.iter() {
+        creatures.insert(creature_entity, transform.translation.truncate());
+    }
+
+    let mut attacks = Vec::new();
+    for (creature_entity, creature, transform) in &mut creature_query.iter() {
+        if !creature.is_attacking() {
+            continue;
+        }
+
+        let translation = transform.translation.truncate();
+        let target = creatures.nearest_within(translation, creature_size.length(), |&entity| {
+            entity == creature_entity
+        });
+
+        if let Some(&(target_entity, target_translation)) = target {
+            let collision = collide(
+                transform.translation,
+                creature_size,
+                target_translation.extend(0.0),
+                creature_size,
+            );
+
+            if collision.is_some() {
+                attacks.push((creature_entity, target_entity));
+            }
+        }
+    }
+
+    let damage = config.attack_damage * clock.step();
+    for (attacker, target) in attacks {
+        let drained = match creature_query.get_mut::<Creature>(target) {
+            Ok(mut target) => target.drain(damage),
+            Err(_) => continue,
+        };
+
+        if let Ok(mut attacker) = creature_query.get_mut::<Creature>(attacker) {
+            attacker.gain(drained);
+        }
+    }
+
+    if !mating.enabled {
+        return;
+    }
+
+    // mate with the nearest touched partner, once per step
+    let mut partners = SpatialGrid::new(creature_size.x());
+    for (creature_entity, creature, transform) in &mut creature_query.iter() {
+        if creature.can_mate(&mating, &metabolism) {
+            partners.insert(creature_entity, transform.translation.truncate());
+        }
+    }
+
+    let mut mated = HashSet::new();
+    let mut matings = Vec::new();
+    for (creature_entity, creature, transform) in &mut creature_query.iter() {
+        if mated.contains(&creature_entity) || !creature.can_mate(&mating, &metabolism) {
+            continue;
+        }
+
+        let partner = partners.nearest_within(
+            transform.translation.truncate(),
+            creature_size.length(),
+            |entity| *entity == creature_entity || mated.contains(entity),
+        );
+
+        if let Some(&(partner_entity, partner_translation)) = partner {
+            let collision = collide(
+                transform.translation,
+                creature_size,
+                partner_translation.extend(0.0),
+                creature_size,
+            );
+
+            if collision.is_some() {
+                mated.insert(creature_entity);
+                mated.insert(partner_entity);
+                matings.push((creature_entity, partner_entity, transform.clone()));
+            }
+        }
+    }
+
+    for (creature_entity, partner_entity, transform) in matings {
+        let (partner_dna, partner_id, partner_generation) =
+            match creature_query.get_mut::<Creature>(partner_entity) {
+                Ok(mut partner) => {
+                    partner.drain(mating.cost);
+                    partner.add_offspring();
+                    (partner.dna().clone(), partner.id(), partner.generation())
+                }
+                Err(_) => continue,
+            };
+
+        if let Ok(mut creature) = creature_query.get_mut::<Creature>(creature_entity) {
+            let child = creature.mate(
+                &partner_dna,
+                partner_id,
+                partner_generation,
+                &mating,
+                &mutation,
+            );
+            commands.spawn((child, transform, Newborn));
+        }
+    }
 }
@@ -0,0 +1,14 @@
+use crate::event::CreatureDied;
+use crate::resource::DeathCount;
+
+use bevy::prelude::*;
+
+pub fn death_count_system(
+    mut died_reader: Local<EventReader<CreatureDied>>,
+    died_events: Res<Events<CreatureDied>>,
+    mut death_count: ResMut<DeathCount>,
+) {
+    for died in died_reader.iter(&died_events) {
+        death_count.record(died.cause);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::component::Creature;
+use crate::config::HallOfFameConfig;
+use crate::event::CreatureDied;
+use crate::hall_of_fame::HallOfFame;
+use crate::resource::SimulationState;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use std::path::Path;
+
+fn save(hall_of_fame: &mut HallOfFame, path: &Path) {
+    if let Err(err) = hall_of_fame.save(path) {
+        eprintln!("Can't save hall of fame to {}: {}", path.display(), err);
+    }
+}
+
+pub fn hall_of_fame_system(
+    mut died_reader: Local<EventReader<CreatureDied>>,
+    mut last_saved_turn: Local<usize>,
+    died_events: Res<Events<CreatureDied>>,
+    config: Res<HallOfFameConfig>,
+    simulation: Res<SimulationState>,
+    mut hall_of_fame: ResMut<HallOfFame>,
+) {
+    for died in died_reader.iter(&died_events) {
+        hall_of_fame.record(&died.record, config.size);
+    }
+
+    let turn_count = simulation.turn_count();
+
+    if let Some(path) = &config.path {
+        if hall_of_fame.is_unsaved() && turn_count >= *last_saved_turn + config.save_interval {
+            save(&mut hall_of_fame, path);
+            *last_saved_turn = turn_count;
+        }
+    }
+}
+
+/// Record the living creatures and save the last turns when the app exits
+pub fn hall_of_fame_exit_system(
+    mut exit_reader: Local<EventReader<AppExit>>,
+    exit_events: Res<Events<AppExit>>,
+    config: Res<HallOfFameConfig>,
+    mut hall_of_fame: ResMut<HallOfFame>,
+    mut creature_query: Query<&Creature>,
+) {
+    if exit_reader.iter(&exit_events).next().is_none() {
+        return;
+    }
+
+    for creature in &mut creature_query.iter() {
+        hall_of_fame.record(&creature.record(), config.size);
+    }
+
+    if let Some(path) = &config.path {
+        if hall_of_fame.is_unsaved() {
+            save(&mut hall_of_fame, path);
+        }
+    }
+}
@@ -1,13 +1,15 @@
 use crate::component::Creature;
+use crate::config::MetabolismConfig;
 use crate::resource::GameSprites;
 use bevy::prelude::*;
 
 pub fn life_display_system(
     sprites: Res<GameSprites>,
+    metabolism: Res<MetabolismConfig>,
     mut creature_query: Query<(&Creature, &mut Handle<ColorMaterial>)>,
 ) {
     for (creature, mut sprite) in &mut creature_query.iter() {
-        *sprite = if creature.will_die() {
+        *sprite = if creature.will_die(&metabolism) {
             sprites.creature()
         } else {
             sprites.creature_filled()
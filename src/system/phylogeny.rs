@@ -0,0 +1,89 @@
+use crate::component::{Creature, Newborn};
+use crate::config::PhylogenyConfig;
+use crate::event::{CreatureBorn, CreatureDied};
+use crate::phylogeny::{Node, Phylogeny};
+use crate::resource::SimulationState;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use std::path::Path;
+
+fn save(phylogeny: &Phylogeny, path: &Path) {
+    if let Err(err) = phylogeny.save(path) {
+        eprintln!("Can't save phylogeny to {}: {}", path.display(), err);
+    }
+}
+
+pub fn phylogeny_system(
+    mut commands: Commands,
+    mut died_reader: Local<EventReader<CreatureDied>>,
+    mut last_saved_turn: Local<usize>,
+    died_events: Res<Events<CreatureDied>>,
+    config: Res<PhylogenyConfig>,
+    simulation: Res<SimulationState>,
+    mut phylogeny: ResMut<Phylogeny>,
+    mut born_events: ResMut<Events<CreatureBorn>>,
+    mut newborn_query: Query<(Entity, &mut Creature, &Newborn)>,
+) {
+    let turn_count = simulation.turn_count();
+
+    // Query order doesn't depend on thread scheduling, so IDs are deterministic
+    for (entity, mut creature, _newborn) in &mut newborn_query.iter() {
+        commands.remove_one::<Newborn>(entity);
+
+        match creature.id() {
+            // Loaded from a snapshot
+            Some(id) => {
+                if !phylogeny.contains(id) {
+                    phylogeny.insert(Node {
+                        id,
+                        parents: creature.parents().to_vec(),
+                        birth_turn: creature.birth_turn(),
+                        death_turn: None,
+                    });
+                }
+            }
+            None => {
+                let id = phylogeny.register(creature.parents().to_vec(), turn_count);
+                creature.register(id, turn_count);
+                born_events.send(CreatureBorn {
+                    parents: creature.parents().len(),
+                    mutations: creature.mutations(),
+                });
+            }
+        }
+    }
+
+    for died in died_reader.iter(&died_events) {
+        if let Some(id) = died.record.id {
+            phylogeny.record_death(id, turn_count);
+            if !config.keep_extinct {
+                phylogeny.prune(id);
+            }
+        }
+    }
+
+    if let Some(path) = &config.path {
+        if turn_count >= *last_saved_turn + config.save_interval {
+            save(&phylogeny, path);
+            *last_saved_turn = turn_count;
+        }
+    }
+}
+
+/// Save the lineage of the last turns when the app exits
+pub fn phylogeny_exit_system(
+    mut exit_reader: Local<EventReader<AppExit>>,
+    exit_events: Res<Events<AppExit>>,
+    config: Res<PhylogenyConfig>,
+    phylogeny: Res<Phylogeny>,
+) {
+    if exit_reader.iter(&exit_events).next().is_none() {
+        return;
+    }
+
+    if let Some(path) = &config.path {
+        save(&phylogeny, path);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::component::{SimulationUi, Wall};
-use crate::constants::{GRID_BOUND, SCALE_F};
+use crate::config::WorldConfig;
 use crate::resource::GameSprites;
 
 use bevy::prelude::*;
@@ -8,6 +8,7 @@ pub fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    world: Res<WorldConfig>,
 ) {
     let creature_texture = asset_server
         .load("creature.png");
@@ -19,8 +20,8 @@ pub fn setup(
         .load("Hack-Regular.ttf");
 
     let mut camera = Camera2dComponents::default();
-    camera.transform.scale = Vec3::splat(SCALE_F);
-    camera.orthographic_projection.far *= SCALE_F;
+    camera.transform.scale = Vec3::splat(world.scale);
+    camera.orthographic_projection.far *= world.scale;
 
     commands
         .insert_resource(GameSprites::new(
@@ -37,7 +38,7 @@ pub fn setup(
                 font,
                 style: TextStyle {
                     color: Color::WHITE,
-                    font_size: 30.0,
+                    font_size: 20.0,
                 },
             },
             style: Style {
@@ -50,7 +51,7 @@ pub fn setup(
 
     let wall_material = materials.add(Color::WHITE.into());
     let thickness = 10.0;
-    let bound = GRID_BOUND + Vec2::splat(thickness * 2.0);
+    let bound = world.bound() + Vec2::splat(thickness * 2.0);
 
     commands
         .spawn(SpriteComponents {
@@ -1,4 +1,7 @@
-use crate::resource::SimulationState;
+use crate::component::{Creature, Newborn};
+use crate::config::{ActionConfig, WorldConfig};
+use crate::resource::{InitialPrograms, SimulationRng, SimulationState};
+use crate::utils::calculate_random_objects;
 
 use bevy::prelude::*;
 
@@ -13,3 +16,29 @@ pub fn prepare_simulation_system(mut simulation: ResMut<SimulationState>) {
             SimulationState::running(*daily_creature_count, *daily_food_count, *turn_interval);
     }
 }
+
+pub fn spawn_program_system(
+    mut commands: Commands,
+    mut rng: ResMut<SimulationRng>,
+    mut programs: ResMut<InitialPrograms>,
+    action_config: Res<ActionConfig>,
+    world: Res<WorldConfig>,
+) {
+    let programs = std::mem::take(&mut programs.0);
+    let transforms = calculate_random_objects(
+        rng.rng(),
+        &world,
+        world.creature,
+        programs.len(),
+        std::iter::empty(),
+    )
+    .collect::<Vec<_>>();
+
+    for (dna, transform) in programs.into_iter().zip(transforms) {
+        commands.spawn((
+            Creature::new(dna, rng.fork(), &action_config),
+            transform,
+            Newborn,
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::component::{Creature, Food, Newborn};
+use crate::dna::Seeder;
+use crate::hall_of_fame::HallOfFame;
+use crate::phylogeny::Phylogeny;
+use crate::resource::{DeathCount, SimulationRng, SimulationState, SnapshotAction, SnapshotConfig};
+use crate::snapshot::Snapshot;
+use crate::stats::StatsRecorder;
+
+use bevy::prelude::*;
+
+pub fn snapshot_system(
+    mut commands: Commands,
+    mut config: ResMut<SnapshotConfig>,
+    mut simulation: ResMut<SimulationState>,
+    mut rng: ResMut<SimulationRng>,
+    mut seeder: ResMut<Seeder>,
+    mut phylogeny: ResMut<Phylogeny>,
+    mut death_count: ResMut<DeathCount>,
+    mut hall_of_fame: ResMut<HallOfFame>,
+    mut recorder: ResMut<StatsRecorder>,
+    mut creature_query: Query<(Entity, &Creature, &Transform)>,
+    mut food_query: Query<(Entity, &Food, &Transform)>,
+) {
+    let turn_count = simulation.turn_count();
+
+    if let Some(interval) = config.autosave_interval {
+        if config.pending.is_none() && turn_count >= config.last_saved_turn + interval {
+            config.pending = Some(SnapshotAction::Save);
+        }
+    }
+
+    match config.pending.take() {
+        Some(SnapshotAction::Save) => {
+            let mut snapshot = Snapshot {
+                seed: rng.seed(),
+                simulation: simulation.snapshot(),
+                creatures: Vec::new(),
+                foods: Vec::new(),
+                phylogeny: phylogeny.nodes().cloned().collect(),
+                deaths: death_count.clone(),
+                hall_of_fame: hall_of_fame.clone(),
+                stats: recorder.counts(),
+            };
+
+            for (_creature_entity, creature, transform) in &mut creature_query.iter() {
+                snapshot
+                    .creatures
+                    .push(creature.snapshot(transform.translation));
+            }
+
+            for (_food_entity, food, transform) in &mut food_query.iter() {
+                if !food.is_ate() {
+                    snapshot.foods.push(food.snapshot(transform.translation));
+                }
+            }
+
+            match snapshot.save(&config.path) {
+                Ok(()) => println!("Saved snapshot to {}", config.path.display()),
+                Err(err) => eprintln!("Can't save snapshot to {}: {}", config.path.display(), err),
+            }
+
+            config.last_saved_turn = turn_count;
+        }
+        Some(SnapshotAction::Load) => match Snapshot::load(&config.path) {
+            Ok(mut snapshot) => {
+                // Checked first so a corrupt lineage leaves the running simulation alone
+                let lineage = match Phylogeny::from_nodes(std::mem::take(&mut snapshot.phylogeny)) {
+                    Ok(lineage) => lineage,
+                    Err(err) => {
+                        eprintln!(
+                            "Can't load snapshot from {}: {}",
+                            config.path.display(),
+                            err
+                        );
+                        return;
+                    }
+                };
+
+                for (creature_entity, _creature, _transform) in &mut creature_query.iter() {
+                    commands.despawn(creature_entity);
+                }
+
+                for (food_entity, _food, _transform) in &mut food_query.iter() {
+                    commands.despawn(food_entity);
+                }
+
+                let seed = snapshot.resume_seed();
+                *rng = SimulationRng::new(seed);
+                seeder.pool_mut().reseed(seed);
+                *simulation = SimulationState::from_snapshot(&snapshot.simulation);
+                // Drop lineage of the current run, loaded creatures are inserted if missing
+                *phylogeny = lineage;
+                *death_count = snapshot.deaths;
+                *hall_of_fame = snapshot.hall_of_fame;
+                recorder.restore(snapshot.stats, snapshot.simulation.turn_count);
+                config.last_saved_turn = snapshot.simulation.turn_count;
+
+                for creature in snapshot.creatures {
+                    let transform = Transform::from_translation(creature.translation.into());
+                    commands.spawn((Creature::from_snapshot(creature), transform, Newborn));
+                }
+
+                for food in snapshot.foods {
+                    let transform = Transform::from_translation(food.translation.into());
+                    commands.spawn((Food::new(), transform));
+                }
+
+                println!("Loaded snapshot from {}", config.path.display());
+            }
+            Err(err) => eprintln!(
+                "Can't load snapshot from {}: {}",
+                config.path.display(),
+                err
+            ),
+        },
+        None => {}
+    }
+}
+
+pub fn snapshot_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut config: ResMut<SnapshotConfig>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        config.pending = Some(SnapshotAction::Save);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        config.pending = Some(SnapshotAction::Load);
+    }
+}
@@ -0,0 +1,38 @@
+use crate::component::{Creature, Food};
+use crate::config::WorldConfig;
+use crate::resource::GameSprites;
+
+use bevy::prelude::*;
+
+/// Attach sprites to creatures and foods spawned by simulation systems
+pub fn sprite_system(
+    mut commands: Commands,
+    sprites: Res<GameSprites>,
+    world: Res<WorldConfig>,
+    mut creature_query: Query<Without<Sprite, (Entity, &Creature, &Transform)>>,
+    mut food_query: Query<Without<Sprite, (Entity, &Food, &Transform)>>,
+) {
+    for (creature_entity, _creature, transform) in &mut creature_query.iter() {
+        commands.insert(
+            creature_entity,
+            SpriteComponents {
+                material: sprites.creature(),
+                sprite: Sprite::new(world.creature_size()),
+                transform: transform.clone(),
+                ..Default::default()
+            },
+        );
+    }
+
+    for (food_entity, _food, transform) in &mut food_query.iter() {
+        commands.insert(
+            food_entity,
+            SpriteComponents {
+                material: sprites.food(),
+                sprite: Sprite::new(world.food_size()),
+                transform: transform.clone(),
+                ..Default::default()
+            },
+        );
+    }
+}
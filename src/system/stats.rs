@@ -0,0 +1,62 @@
+use crate::component::Creature;
+use crate::event::{CreatureBorn, CreatureDied, FoodEaten};
+use crate::resource::{SimulationState, TurnLimit};
+use crate::stats::StatsRecorder;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+pub fn stats_system(
+    mut died_reader: Local<EventReader<CreatureDied>>,
+    mut born_reader: Local<EventReader<CreatureBorn>>,
+    mut eaten_reader: Local<EventReader<FoodEaten>>,
+    died_events: Res<Events<CreatureDied>>,
+    born_events: Res<Events<CreatureBorn>>,
+    eaten_events: Res<Events<FoodEaten>>,
+    simulation: Res<SimulationState>,
+    mut recorder: ResMut<StatsRecorder>,
+    mut creature_query: Query<&Creature>,
+) {
+    let stats = recorder.current_mut();
+
+    for died in died_reader.iter(&died_events) {
+        stats.deaths.record(died.cause);
+    }
+
+    for born in born_reader.iter(&born_events) {
+        stats.births += 1;
+        stats.mutations += born.mutations;
+        if born.parents == 0 {
+            stats.seeded += 1;
+        }
+    }
+
+    stats.food_eaten += eaten_reader.iter(&eaten_events).count();
+
+    let turn_count = simulation.turn_count();
+
+    if turn_count != recorder.last_turn() {
+        if let Err(err) = recorder.finish_turn(turn_count, creature_query.iter().iter()) {
+            eprintln!("Can't record stats, stop recording: {}", err);
+        }
+    }
+}
+
+/// Store the run summary and exit once the turn limit is reached
+pub fn turn_limit_system(
+    limit: Res<TurnLimit>,
+    simulation: Res<SimulationState>,
+    recorder: Res<StatsRecorder>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+    mut creature_query: Query<&Creature>,
+) {
+    let turn_count = simulation.turn_count();
+
+    if !limit.is_reached(turn_count) {
+        return;
+    }
+
+    *limit.summary.lock().unwrap() =
+        Some(recorder.summary(turn_count, creature_query.iter().iter()));
+    app_exit_events.send(AppExit);
+}
@@ -0,0 +1,57 @@
+use crate::resource::{SimulationClock, SimulationState, TurnLimit};
+
+use bevy::ecs::{Resources, Schedule, World};
+use bevy::prelude::*;
+
+pub const SIMULATION_STAGE: &str = "simulation";
+/// Runs after commands of `SIMULATION_STAGE` are applied
+pub const TURN_STAGE: &str = "turn";
+
+/// Run simulation schedule `steps_per_frame` times with a fixed logical step
+///
+/// Systems inside `schedule` must use `SimulationClock` instead of `Time`,
+/// steps stop early once `TurnLimit` is reached
+pub fn simulation_step_system(
+    mut schedule: Schedule,
+) -> impl FnMut(&mut World, &mut Resources) + Send + Sync + 'static {
+    move |world: &mut World, resources: &mut Resources| {
+        let steps = resources
+            .get::<SimulationClock>()
+            .expect("SimulationClock resource")
+            .steps_per_frame();
+
+        schedule.initialize(world, resources);
+
+        for _ in 0..steps {
+            if is_limit_reached(resources) {
+                break;
+            }
+
+            schedule.run(world, resources);
+        }
+    }
+}
+
+fn is_limit_reached(resources: &Resources) -> bool {
+    let limit = resources.get::<TurnLimit>().expect("TurnLimit resource");
+    let simulation = resources
+        .get::<SimulationState>()
+        .expect("SimulationState resource");
+
+    limit.is_reached(simulation.turn_count())
+}
+
+pub fn speed_control_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut clock: ResMut<SimulationClock>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Equals) {
+        let steps = clock.steps_per_frame() * 2;
+        clock.set_steps_per_frame(steps);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        let steps = clock.steps_per_frame() / 2;
+        clock.set_steps_per_frame(steps);
+    }
+}
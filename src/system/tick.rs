@@ -1,4 +1,8 @@
-use crate::component::Creature;
+use crate::component::{Creature, Food};
+use crate::config::{ActionConfig, InterpreterConfig, SensorConfig, WorldConfig};
+use crate::event::CreatureDied;
+use crate::resource::SimulationClock;
+use crate::sensor::Surroundings;
 
 use bevy::prelude::*;
 use bevy::tasks::prelude::*;
@@ -6,17 +10,56 @@ use bevy::tasks::prelude::*;
 pub fn tick_system(
     mut commands: Commands,
     pool: Res<AsyncComputeTaskPool>,
-    time: Res<Time>,
+    clock: Res<SimulationClock>,
+    config: Res<InterpreterConfig>,
+    sensor_config: Res<SensorConfig>,
+    action_config: Res<ActionConfig>,
+    world: Res<WorldConfig>,
+    mut died_events: ResMut<Events<CreatureDied>>,
     mut creature_query: Query<(Entity, &mut Creature, &Transform)>,
+    mut food_query: Query<(&Food, &Transform)>,
 ) {
-    let delta = time.delta_seconds;
+    let delta = clock.step();
+    let config = *config;
+    let action_config = *action_config;
+    let sensors = &sensor_config.sensors;
+
+    let surroundings = {
+        let mut food_iter = food_query.iter();
+        let mut creature_iter = creature_query.iter();
+
+        Surroundings::new(
+            *world,
+            food_iter
+                .iter()
+                .map(|(_food, transform)| transform.translation.truncate()),
+            creature_iter.iter().map(|(entity, creature, transform)| {
+                (entity, creature.signal(), transform.translation.truncate())
+            }),
+        )
+    };
+    let surroundings = &surroundings;
+
     creature_query.iter().par_iter(1).for_each(
         &pool,
         move |(creature_entity, mut creature, transform)| {
-            if let Err(_) = creature.tick(transform.translation.truncate(), delta) {
-                // invalid code
-                commands.despawn(creature_entity);
+            if creature.tick(delta) {
+                let translation = transform.translation.truncate();
+                let input = surroundings.sense(sensors, creature_entity, &creature, translation);
+                // creatures with invalid code are despawned below
+                let _ = creature.think(&input, &config, &action_config);
             }
         },
     );
+
+    // Despawn sequentially so command order doesn't depend on thread scheduling
+    for (creature_entity, creature, _transform) in &mut creature_query.iter() {
+        if let Some(err) = creature.error() {
+            commands.despawn(creature_entity);
+            died_events.send(CreatureDied {
+                cause: err.into(),
+                record: creature.record(),
+            });
+        }
+    }
 }
@@ -1,93 +1,134 @@
-use crate::component::{Creature, Food};
-use crate::resource::{GameSprites, SimulationState};
-use crate::utils::{calculate_random_objects, is_out_of_box};
+use crate::component::{Creature, DeathCause, Food, Newborn};
+use crate::config::{ActionConfig, MatingConfig, MetabolismConfig, MutationConfig, WorldConfig};
+use crate::dna::{Seeder, DNA};
+use crate::event::CreatureDied;
+use crate::resource::{SimulationClock, SimulationRng, SimulationState};
+use crate::utils::calculate_random_objects;
 
 use bevy::prelude::*;
+use rand::Rng;
+
+use std::cmp::Ordering;
 
 pub fn turn_system(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<SimulationClock>,
+    metabolism: Res<MetabolismConfig>,
+    action_config: Res<ActionConfig>,
+    mating: Res<MatingConfig>,
+    mutation: Res<MutationConfig>,
+    world: Res<WorldConfig>,
     mut simulation: ResMut<SimulationState>,
-    sprites: Res<GameSprites>,
+    mut rng: ResMut<SimulationRng>,
+    seeder: Res<Seeder>,
+    mut died_events: ResMut<Events<CreatureDied>>,
     mut creature_query: Query<(Entity, &mut Creature, &Transform)>,
     mut food_query: Query<(&Food, &Transform)>,
 ) {
+    if !simulation.step(clock.step()) {
+        return;
+    }
+
     if let SimulationState::Running {
         daily_creature_count,
         daily_food_count,
-        turn_count,
-        turn_timer,
-    } = &mut *simulation
+        ..
+    } = &*simulation
     {
-        turn_timer.tick(time.delta_seconds);
-
-        if !turn_timer.finished {
-            return;
-        }
-
-        *turn_count += 1;
+        let elites = fittest_dna(
+            &mut creature_query,
+            seeder.elite_count(),
+            &metabolism,
+            &world,
+        );
 
         // Process creature
         for (creature_entity, mut creature, transform) in &mut creature_query.iter() {
-            if creature.will_die() || is_out_of_box(transform.translation) {
+            let cause = creature.death_cause(&metabolism).or_else(|| {
+                if world.is_out_of_box(transform.translation.truncate()) {
+                    Some(DeathCause::OutOfBox)
+                } else {
+                    None
+                }
+            });
+
+            if let Some(cause) = cause {
                 commands.despawn(creature_entity);
+                died_events.send(CreatureDied {
+                    cause,
+                    record: creature.record(),
+                });
             } else {
-                if let Some(child) = creature.try_duplicate() {
-                    commands
-                        .spawn(SpriteComponents {
-                            material: sprites.creature(),
-                            sprite: Sprite::new(Creature::INIT_SIZE),
-                            transform: transform.clone(),
-                            ..Default::default()
-                        })
-                        .with(child);
+                if let Some(child) = creature.try_duplicate(&metabolism, &action_config, &mutation)
+                {
+                    commands.spawn((child, transform.clone(), Newborn));
                 }
             }
 
-            creature.time_pass();
+            creature.time_pass(&metabolism);
         }
 
         // Spawn creatures
         let mut creature_iter = creature_query.iter();
 
         for transform in calculate_random_objects(
-            Creature::INIT_X,
-            Creature::INIT_Y,
+            rng.rng(),
+            &world,
+            world.creature,
             *daily_creature_count,
             creature_iter
                 .iter()
                 .map(|(_, _, transform)| transform.translation),
         ) {
-            commands
-                .spawn(SpriteComponents {
-                    material: sprites.creature(),
-                    sprite: Sprite::new(Creature::INIT_SIZE),
-                    transform,
-                    ..Default::default()
-                })
-                .with(Creature::new());
+            let sexual = mating.enabled && rng.rng().gen::<f32>() < mating.sexual_ratio;
+            let dna = seeder.take(rng.rng(), &elites, &mutation);
+            let creature = Creature::new(dna, rng.fork(), &action_config).sexual(sexual);
+            commands.spawn((creature, transform, Newborn));
         }
 
         // Spawn foods
         let mut food_iter = food_query.iter();
 
         for transform in calculate_random_objects(
-            Food::INIT_X,
-            Food::INIT_Y,
+            rng.rng(),
+            &world,
+            world.food,
             *daily_food_count,
             food_iter
                 .iter()
                 .take(*daily_food_count * 2)
                 .map(|(_food, transform)| transform.translation),
         ) {
-            commands
-                .spawn(SpriteComponents {
-                    material: sprites.food(),
-                    sprite: Sprite::new(Food::INIT_SIZE),
-                    transform,
-                    ..Default::default()
-                })
-                .with(Food::new());
+            commands.spawn((Food::new(), transform));
         }
     }
 }
+
+/// DNA of the `count` creatures surviving this turn with the most life
+fn fittest_dna(
+    query: &mut Query<(Entity, &mut Creature, &Transform)>,
+    count: usize,
+    metabolism: &MetabolismConfig,
+    world: &WorldConfig,
+) -> Vec<DNA> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let mut ranked: Vec<(f32, Entity)> = query
+        .iter()
+        .iter()
+        .filter(|(_, creature, transform)| {
+            !creature.will_die(metabolism) && !world.is_out_of_box(transform.translation.truncate())
+        })
+        .map(|(entity, creature, _)| (creature.life(), entity))
+        .collect();
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    ranked
+        .into_iter()
+        .take(count)
+        .filter_map(|(_, entity)| query.get::<Creature>(entity).ok())
+        .map(|creature| creature.dna().clone())
+        .collect()
+}
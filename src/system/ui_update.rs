@@ -1,28 +1,117 @@
-use crate::component::{Creature, SimulationUi};
-use crate::resource::SimulationState;
+use crate::component::{Creature, DeathCause, SimulationUi};
+use crate::config::MutationConfig;
+use crate::dna::{GenomeKind, Seeder};
+use crate::hall_of_fame::{Category, HallOfFame};
+use crate::histogram::Histogram;
+use crate::resource::{DeathCount, SimulationState};
 
 use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
 use bevy::prelude::*;
 
+/// Most bins of the generation histogram
+const GENERATION_BINS: usize = 10;
+/// Length of the longest histogram bar
+const BAR_WIDTH: usize = 20;
+
 pub fn ui_update_system(
     diagnostics: Res<Diagnostics>,
     simulation: Res<SimulationState>,
+    death_count: Res<DeathCount>,
+    mutation: Res<MutationConfig>,
+    seeder: Res<Seeder>,
+    hall_of_fame: Res<HallOfFame>,
     mut creature_query: Query<(&Creature,)>,
     mut ui_query: Query<(&mut Text, &SimulationUi)>,
 ) {
-    let mut gen = [0; 10];
-    let mut old = [0; 20];
+    let mut generations = Vec::new();
+    let mut olds = Vec::new();
+    let mut sexual = 0;
+    let mut asexual = 0;
+    let mut rate_sum = 0.0;
+    let mut rate_min = f32::INFINITY;
+    let mut rate_max: f32 = 0.0;
+    let mut kinds = [0; GenomeKind::ALL.len()];
 
     for (creature,) in &mut creature_query.iter() {
-        gen[creature.generation()] += 1;
-        old[creature.old()] += 1;
+        generations.push(creature.generation());
+        olds.push(creature.old());
+
+        if creature.is_sexual() {
+            sexual += 1;
+        } else {
+            asexual += 1;
+        }
+
+        kinds[creature.dna().kind() as usize] += 1;
+
+        let rate = creature.dna().mutation_rate(&mutation);
+        rate_sum += rate;
+        rate_min = rate_min.min(rate);
+        rate_max = rate_max.max(rate);
     }
 
+    let population = sexual + asexual;
+    let mutation_rate = if population > 0 {
+        format!(
+            "mean {:.3}, min {:.3}, max {:.3}",
+            rate_sum / population as f32,
+            rate_min,
+            rate_max
+        )
+    } else {
+        String::from("-")
+    };
+
+    let generation_chart = Histogram::linear(&generations, GENERATION_BINS).bar_chart(BAR_WIDTH);
+    // ages spread over orders of magnitude as runs go on
+    let old_chart = Histogram::log2(&olds).bar_chart(BAR_WIDTH);
+
+    let genomes = GenomeKind::ALL
+        .iter()
+        .map(|&kind| format!("{:?}: {}", kind, kinds[kind as usize]))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let pool = seeder.pool().metrics();
+
+    let champions = Category::ALL
+        .iter()
+        .map(|&category| {
+            let best = hall_of_fame.entries(category).first();
+            format!(
+                "{:?}: {}",
+                category,
+                best.map_or(0, |record| category.score(record))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let deaths = DeathCause::ALL
+        .iter()
+        .map(|&cause| format!("{:?}: {}", cause, death_count.get(cause)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
     if let SimulationState::Running { turn_count, .. } = &*simulation {
         for (mut text, _ui) in &mut ui_query.iter() {
             if let Some(fps) = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS) {
                 if let Some(average) = fps.average() {
-                    text.value = format!("TURN: {}, FPS: {}\nGEN: {:?}\nOLD: {:?}", turn_count, average, gen, old);
+                    text.value = format!(
+                        "TURN: {}, FPS: {}\nDEATH: {}\nSEXUAL: {}, ASEXUAL: {}\nMUTATION RATE: {}\nGENOME: {}\nPOOL: rejected {:.1}% of {}\nHALL OF FAME: {}\nGEN:\n{}OLD:\n{}",
+                        turn_count,
+                        average,
+                        deaths,
+                        sexual,
+                        asexual,
+                        mutation_rate,
+                        genomes,
+                        pool.rejection_rate() * 100.0,
+                        pool.generated,
+                        champions,
+                        generation_chart,
+                        old_chart
+                    );
                 }
             }
         }
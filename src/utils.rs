@@ -1,77 +1,213 @@
-use crate::constants::{BaseType, BASE_UNIT, GRID_SIZE};
+use crate::config::WorldConfig;
+use crate::constants::{BaseType, BASE_UNIT};
 
 use bevy::prelude::{Transform, Vec2, Vec3};
 use grid::Grid;
-use num_traits::Pow;
-use rand::{seq::IteratorRandom, thread_rng};
+use rand::rngs::StdRng;
+use rand::{seq::IteratorRandom, Error, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
 
+/// Zigzag encoding, units 0, -1, 1, -2, 2, ... are mapped to 0, 1, 2, 3, 4, ...
 pub fn convert_to_unit(val: f32) -> BaseType {
-    (val / BASE_UNIT) as BaseType
+    let unit = (val / BASE_UNIT) as isize;
+
+    if unit >= 0 {
+        (unit as BaseType).saturating_mul(2)
+    } else {
+        (-(unit + 1) as BaseType)
+            .saturating_mul(2)
+            .saturating_add(1)
+    }
 }
 
+/// Inverse of `convert_to_unit`
 pub fn convert_from_unit(unit: BaseType) -> f32 {
-    (unit as f32) * BASE_UNIT
+    let magnitude = (unit / 2) as f32;
+
+    if unit & 1 == 0 {
+        magnitude * BASE_UNIT
+    } else {
+        -(magnitude + 1.0) * BASE_UNIT
+    }
 }
 
-pub fn convert_vec2_to_unit(vec2: Vec2) -> (BaseType, BaseType) {
-    (convert_to_unit(vec2.x()), convert_to_unit(vec2.y()))
+/// SplitMix64 output function, close inputs give unrelated outputs
+pub fn splitmix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
-pub fn calculate_move_cost(distance: f32) -> f32 {
-    distance.pow(1.2) / 80.0
+/// Base of the streams derived from `seed` by `stream_seed`
+pub fn seed_base(seed: u64) -> u64 {
+    StdRng::seed_from_u64(seed).gen()
 }
 
-pub fn is_out_of_box(translation: Vec3) -> bool {
-    let max_x = (GRID_SIZE.0 / 2) as f32;
-    let max_y = (GRID_SIZE.1 / 2) as f32;
-    let min_x = -max_x;
-    let min_y = -max_y;
-
-    if translation.x() > min_x
-        && translation.x() < max_x
-        && translation.y() > min_y
-        && translation.y() < max_y
-    {
-        false
-    } else {
-        true
+/// Seed of the `idx`th stream of `base`
+///
+/// Streams of different seeds don't share seeds, unlike `seed ^ idx`
+pub fn stream_seed(base: u64, idx: u64) -> u64 {
+    splitmix(base.wrapping_add(idx))
+}
+
+/// Position in the stream of a `ResumableRng`
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RngState {
+    pub seed: [u8; 32],
+    /// 32-bit words drawn since seeding
+    pub word_pos: u64,
+}
+
+/// Same generator as `StdRng`, which can be saved and resumed at the same draw
+///
+/// Drawn words are counted, `ChaCha20Rng::get_word_pos` overflows before the first draw
+pub struct ResumableRng {
+    seed: [u8; 32],
+    word_pos: u64,
+    rng: ChaCha20Rng,
+}
+
+impl ResumableRng {
+    pub fn state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            word_pos: self.word_pos,
+        }
+    }
+
+    pub fn from_state(state: RngState) -> Self {
+        let mut rng = Self::from_seed(state.seed);
+        rng.rng.set_word_pos(state.word_pos.into());
+        rng.word_pos = state.word_pos;
+        rng
+    }
+}
+
+impl SeedableRng for ResumableRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            seed,
+            word_pos: 0,
+            rng: ChaCha20Rng::from_seed(seed),
+        }
+    }
+}
+
+impl RngCore for ResumableRng {
+    fn next_u32(&mut self) -> u32 {
+        self.word_pos += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.word_pos += 2;
+        self.rng.next_u64()
+    }
+
+    // a partly used word is dropped like a whole one
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.word_pos += dest.chunks(4).len() as u64;
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.word_pos += dest.chunks(4).len() as u64;
+        self.rng.try_fill_bytes(dest)
     }
 }
 
+/// Same random source for every test
+#[cfg(test)]
+pub fn test_rng() -> ResumableRng {
+    ResumableRng::seed_from_u64(0)
+}
+
+pub fn convert_vec2_to_unit(vec2: Vec2) -> (BaseType, BaseType) {
+    (convert_to_unit(vec2.x()), convert_to_unit(vec2.y()))
+}
+
+/// Translations of `count` objects of `object` size not overlapping `translations`
 pub fn calculate_random_objects(
-    object_width: usize,
-    object_height: usize,
+    rng: &mut impl Rng,
+    world: &WorldConfig,
+    object: (usize, usize),
     count: usize,
     translations: impl Iterator<Item = Vec3>,
 ) -> impl Iterator<Item = Transform> {
-    let mut rng = thread_rng();
-
-    let mut grid = Grid::new(GRID_SIZE.1 / object_height, GRID_SIZE.0 / object_width);
+    let (world_width, world_height) = world.size;
+    let (object_width, object_height) = object;
+    let mut grid = Grid::new(world_height / object_height, world_width / object_width);
 
     for translation in translations {
-        let x = (((GRID_SIZE.0 / 2) as f32 + translation.x()).max(0.0) as usize) / object_width;
+        let x = (((world_width / 2) as f32 + translation.x()).max(0.0) as usize) / object_width;
         let x = x.min(grid.cols() - 1);
-        let y = (((GRID_SIZE.1 / 2) as f32 + translation.y()).max(0.0) as usize) / object_height;
+        let y = (((world_height / 2) as f32 + translation.y()).max(0.0) as usize) / object_height;
         let y = y.min(grid.rows() - 1);
 
         grid[y][x] = true;
     }
 
-    let min_x = -(((GRID_SIZE.0 / 2) - object_width) as f32);
-    let min_y = -(((GRID_SIZE.1 / 2) - object_height) as f32);
+    let min_x = -(((world_width / 2) - object_width) as f32);
+    let min_y = -(((world_height / 2) - object_height) as f32);
 
     grid.iter_mut()
         .enumerate()
         .filter_map(|(i, v)| if *v { None } else { Some(i) })
-        .choose_multiple(&mut rng, count)
+        .choose_multiple(rng, count)
         .into_iter()
         .map(move |idx| {
             let y = idx / grid.cols();
             let x = idx - y * grid.cols();
             Transform::from_translation(Vec3::new(
-                ((x * object_width) as f32 - (GRID_SIZE.0 / 2) as f32).max(min_x),
-                ((y * object_height) as f32 - (GRID_SIZE.1 / 2) as f32).max(min_y),
+                ((x * object_width) as f32 - (world_width / 2) as f32).max(min_x),
+                ((y * object_height) as f32 - (world_height / 2) as f32).max(min_y),
                 0.0,
             ))
         })
 }
+
+#[test]
+fn unit_encoding_test() {
+    let pairs = [
+        (0.0, 0),
+        (-0.5, 1),
+        (0.5, 2),
+        (-1.0, 3),
+        (1.0, 4),
+        (-100.0, 399),
+        (100.0, 400),
+    ];
+
+    for &(val, unit) in pairs.iter() {
+        assert_eq!(convert_to_unit(val), unit);
+        assert_eq!(convert_from_unit(unit), val);
+    }
+
+    // fractions of a unit are truncated toward zero
+    assert_eq!(convert_to_unit(0.9), 2);
+    assert_eq!(convert_to_unit(-0.9), 1);
+    assert_eq!(convert_vec2_to_unit(Vec2::new(-1.0, 1.0)), (3, 4));
+}
+
+#[test]
+fn resumable_rng_test() {
+    let mut rng = ResumableRng::seed_from_u64(3);
+    let mut std_rng = StdRng::seed_from_u64(3);
+    assert_eq!(rng.gen::<[u64; 4]>(), std_rng.gen::<[u64; 4]>());
+
+    let mut resumed = ResumableRng::from_state(ResumableRng::seed_from_u64(3).state());
+    assert_eq!(
+        resumed.gen::<[u64; 4]>(),
+        StdRng::seed_from_u64(3).gen::<[u64; 4]>()
+    );
+
+    rng.gen::<u32>();
+    rng.fill(&mut [0u8; 7]);
+    let mut resumed = ResumableRng::from_state(rng.state());
+    assert_eq!(rng.gen::<[u32; 32]>(), resumed.gen::<[u32; 32]>());
+    assert_eq!(rng.gen::<u64>(), resumed.gen::<u64>());
+}